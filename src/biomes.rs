@@ -0,0 +1,129 @@
+use crate::{
+	tiletypes::{known_tiles, TileType},
+	worldgen::Threshold,
+};
+use strum_macros::Display;
+
+/// How many tiles below y = 0 it takes for depth to go from 0.0 to 1.0.
+pub const DEPTH_SCALE: f64 = 1000.0;
+
+/// How far the thresholds that deepen are lowered at `depth`.
+pub fn depth_shift(depth: f64) -> f64 {
+	depth.clamp(0.0, 1.0) * 0.1
+}
+
+#[derive(Copy, Clone, PartialEq, Display)]
+#[strum(serialize_all = "snake_case")]
+pub enum Biome {
+	SandDesert,
+	Caves,
+	FloodedCaverns,
+	MagmaDepths,
+}
+
+impl Biome {
+	/// Every biome, in declaration order, so `Biome as usize` indexes it.
+	pub const ALL: [Biome; 4] = [
+		Biome::SandDesert,
+		Biome::Caves,
+		Biome::FloodedCaverns,
+		Biome::MagmaDepths,
+	];
+
+	/// Picks a biome from the low frequency biome noise, biased towards the deeper
+	/// biomes the further down `depth` is.
+	pub fn from_noise(noise: f64, depth: f64) -> Self {
+		let v = noise + depth.clamp(-1.0, 1.0) * 0.6;
+
+		if v < -0.4 {
+			Biome::SandDesert
+		} else if v < 0.3 {
+			Biome::Caves
+		} else if v < 0.7 {
+			Biome::FloodedCaverns
+		} else {
+			Biome::MagmaDepths
+		}
+	}
+
+	/// Terrain palette for this biome. Deeper terrain is denser, so the solid bands marked with
+	/// `deepens` shift towards the harder tiles the further down it is generated, see
+	/// `depth_shift`.
+	pub fn thresholds(&self) -> Vec<Threshold> {
+		let t = |below: f64, tile_type: TileType| Threshold {
+			below,
+			tile_type,
+			deepens: false,
+		};
+		let d = |below: f64, tile_type: TileType| Threshold {
+			below,
+			tile_type,
+			deepens: true,
+		};
+		let tiles = known_tiles();
+
+		match self {
+			Biome::SandDesert => vec![
+				t(0.0, TileType::EMPTY),
+				d(0.25, tiles.sand),
+				d(0.4, tiles.dirt),
+				t(f64::INFINITY, tiles.gravel),
+			],
+			Biome::Caves => vec![
+				t(0.0, TileType::EMPTY),
+				t(0.01, tiles.moss),
+				d(0.2, tiles.dirt),
+				t(f64::INFINITY, tiles.gravel),
+			],
+			Biome::FloodedCaverns => vec![
+				t(-0.25, tiles.water),
+				t(0.0, TileType::EMPTY),
+				t(0.05, tiles.moss),
+				d(0.3, tiles.dirt),
+				t(f64::INFINITY, tiles.gravel),
+			],
+			Biome::MagmaDepths => vec![
+				t(-0.45, tiles.magma),
+				t(0.0, TileType::EMPTY),
+				d(0.1, tiles.dirt),
+				t(f64::INFINITY, tiles.gravel),
+			],
+		}
+	}
+
+	/// Liquid that fills the pools carved into this biome's cave floors.
+	pub fn pool_liquid(&self) -> TileType {
+		let tiles = known_tiles();
+
+		match self {
+			Biome::SandDesert => tiles.oil,
+			Biome::Caves | Biome::FloodedCaverns => tiles.water,
+			Biome::MagmaDepths => tiles.magma,
+		}
+	}
+
+	/// Light source hung from this biome's cave ceilings, and the chance (one in n)
+	/// of each placement attempt producing one.
+	pub fn ceiling_light(&self) -> Option<(TileType, u32)> {
+		let tiles = known_tiles();
+
+		match self {
+			Biome::SandDesert => Some((tiles.lantern, 3)),
+			Biome::Caves => Some((tiles.lantern, 2)),
+			Biome::FloodedCaverns => Some((tiles.lantern, 4)),
+			Biome::MagmaDepths => None,
+		}
+	}
+
+	/// Tile that structure veins embed in this biome's rock.
+	pub fn vein_tile(&self) -> Option<TileType> {
+		let tiles = known_tiles();
+
+		match self {
+			Biome::SandDesert => Some(tiles.sand),
+			Biome::Caves => Some(tiles.gravel),
+			Biome::FloodedCaverns => Some(tiles.moss),
+			Biome::MagmaDepths => Some(tiles.magma),
+		}
+	}
+}
@@ -0,0 +1,368 @@
+use crate::{
+	grid::{xorshift_from_coord, Chunk, ChunkTier, Map, MapTile},
+	sky::Daylight,
+	sprites::Sprites,
+	tilephysics::update_outline_sprite_event,
+	CHUNK_SIZE, TILE_SIZE,
+};
+use bevy::{
+	prelude::{
+		App, Assets, BuildChildren, Commands, Component, DespawnRecursiveExt, Entity, Handle,
+		Image, IntoSystemConfigs, Last, Plugin, Query, Res, ResMut, Transform, UVec2, With,
+		Without,
+	},
+	render::{
+		render_asset::RenderAssetUsages,
+		render_resource::{Extent3d, TextureDimension, TextureFormat},
+	},
+	sprite::{Anchor, Sprite, SpriteBundle},
+};
+
+/// Draws every rendered chunk as three sprites instead of one per tile: the tile sprites, their
+/// outlines and the light overlay are each composited into a per-chunk image, and only the tiles
+/// in a chunk's `redraw` set are copied again when something changes.
+pub struct ChunkRender;
+
+impl Plugin for ChunkRender {
+	fn build(&self, app: &mut App) {
+		app.add_systems(
+			Last,
+			(detach_chunk_layers, attach_chunk_layers, draw_chunks)
+				.chain()
+				.after(update_outline_sprite_event),
+		);
+	}
+}
+
+#[derive(Component)]
+struct ChunkLayers {
+	tiles: Handle<Image>,
+	outlines: Handle<Image>,
+	/// One pixel per tile, stretched over the chunk.
+	light: Handle<Image>,
+}
+
+const BYTES_PER_PIXEL: usize = 4;
+
+/// Gives the chunks in the rendered tier their layers. The layers start out empty, so every
+/// tile of the chunk is drawn again.
+fn attach_chunk_layers(
+	mut commands: Commands,
+	mut map: ResMut<Map>,
+	mut images: ResMut<Assets<Image>>,
+	q_chunks: Query<(Entity, &Chunk), Without<ChunkLayers>>,
+) {
+	let chunk_size = UVec2::new(CHUNK_SIZE.0 as u32, CHUNK_SIZE.1 as u32);
+	let layer_size = chunk_size * TILE_SIZE;
+
+	for (entity, chunk) in q_chunks.iter() {
+		match map.get_mut(&(chunk.0.x, chunk.0.y)) {
+			Some(map_chunk) if map_chunk.tier == ChunkTier::Rendered => map_chunk.redraw_all(),
+			_ => continue,
+		}
+
+		let layers = ChunkLayers {
+			tiles: images.add(layer_image(layer_size)),
+			outlines: images.add(layer_image(layer_size)),
+			light: images.add(layer_image(chunk_size)),
+		};
+
+		commands
+			.entity(entity)
+			.with_children(|parent| {
+				for (texture, z) in [
+					(layers.tiles.clone(), 0.0),
+					(layers.outlines.clone(), 1.0),
+					(layers.light.clone(), 3.0),
+				] {
+					parent.spawn(SpriteBundle {
+						texture,
+						sprite: Sprite {
+							anchor: Anchor::BottomLeft,
+							custom_size: Some(layer_size.as_vec2()),
+							..Default::default()
+						},
+						// the chunk's origin is the center of its bottom left tile
+						transform: Transform::from_xyz(
+							-(TILE_SIZE.x as f32) * 0.5,
+							-(TILE_SIZE.y as f32) * 0.5,
+							z,
+						),
+						..Default::default()
+					});
+				}
+			})
+			.insert(layers);
+	}
+}
+
+/// Drops the layers of chunks that left the rendered tier, their images are freed with them.
+fn detach_chunk_layers(
+	mut commands: Commands,
+	map: Res<Map>,
+	q_chunks: Query<(Entity, &Chunk), With<ChunkLayers>>,
+) {
+	for (entity, chunk) in q_chunks.iter() {
+		if map
+			.get(&(chunk.0.x, chunk.0.y))
+			.is_some_and(|c| c.tier == ChunkTier::Rendered)
+		{
+			continue;
+		}
+
+		commands
+			.entity(entity)
+			.despawn_descendants()
+			.remove::<ChunkLayers>();
+	}
+}
+
+fn draw_chunks(
+	mut map: ResMut<Map>,
+	sprites: Res<Sprites>,
+	daylight: Res<Daylight>,
+	mut images: ResMut<Assets<Image>>,
+	q_chunks: Query<(&Chunk, &ChunkLayers)>,
+) {
+	for (chunk, layers) in q_chunks.iter() {
+		let map_chunk = match map.get_mut(&(chunk.0.x, chunk.0.y)) {
+			Some(v) => v,
+			None => continue,
+		};
+
+		if map_chunk.redraw.is_empty() {
+			continue;
+		}
+
+		// the layers' pixel data is taken out while drawing, so the tile textures can be
+		// read from the same assets
+		let mut tile_data = take_image_data(&mut images, &layers.tiles);
+		let mut outline_data = take_image_data(&mut images, &layers.outlines);
+		let mut light_data = take_image_data(&mut images, &layers.light);
+
+		for key in std::mem::take(&mut map_chunk.redraw) {
+			let maptile = *map_chunk.tile(key.0, key.1);
+
+			let drawn = draw_tile_sprite(&mut tile_data, key, &maptile, &sprites, &images)
+				&& draw_tile_outline(&mut outline_data, key, &maptile, &sprites, &images);
+
+			let light_index = pixel_index(
+				CHUNK_SIZE.0 as u32,
+				key.0 as u32,
+				(CHUNK_SIZE.1 - 1 - key.1) as u32,
+			);
+
+			light_data[light_index..light_index + BYTES_PER_PIXEL]
+				.copy_from_slice(&light_overlay(&maptile, daylight.0));
+
+			// textures are loaded asynchronously, try again next frame
+			if !drawn {
+				map_chunk.redraw.insert(key);
+			}
+		}
+
+		put_image_data(&mut images, &layers.tiles, tile_data);
+		put_image_data(&mut images, &layers.outlines, outline_data);
+		put_image_data(&mut images, &layers.light, light_data);
+	}
+}
+
+/// Pixel of the light layer over a tile, lit by its light and the sky's. The overlay can only be
+/// alpha blended over the tile, so the tile is darkened down to its dimmest light channel and the
+/// brighter channels are painted over it, as bright as they would make a tile of middling
+/// brightness.
+fn light_overlay(maptile: &MapTile, daylight: u8) -> [u8; 4] {
+	if !maptile.lit {
+		return [0, 0, 0, 0];
+	}
+
+	let sky_light = (maptile.sky_light as u16 * daylight as u16 / u8::MAX as u16) as u8;
+	let light = maptile.light.map(|c| c.saturating_add(sky_light));
+
+	let dimmest = light.into_iter().min().unwrap_or(0);
+	let darkness = u8::MAX - dimmest;
+
+	if darkness == 0 {
+		return [0, 0, 0, 0];
+	}
+
+	let [r, g, b] =
+		light.map(|c| ((c - dimmest) as u16 * u8::MAX as u16 / darkness as u16 / 2) as u8);
+
+	[r, g, b, darkness]
+}
+
+/// Copies the tile's texture into its block of the tile layer, turned and flipped the same way
+/// for every coordinate. Returns false if the texture isn't loaded yet.
+fn draw_tile_sprite(
+	layer: &mut [u8],
+	key: (u8, u8),
+	maptile: &MapTile,
+	sprites: &Sprites,
+	images: &Assets<Image>,
+) -> bool {
+	let tile_type = maptile.tile_type;
+
+	if !tile_type.is_visible() {
+		clear_block(layer, key);
+		return true;
+	}
+
+	let textures = sprites.tiles.get(&tile_type.to_string()).unwrap();
+
+	let (index, quarter_turns, flip) = if !tile_type.is_liquid() {
+		let i = xorshift_from_coord(maptile.tile_coord);
+
+		let (quarter_turns, flip) = if tile_type.morph_sprite() {
+			(
+				i.rem_euclid(4),
+				match (i / 10) % 3 {
+					1 => (false, true), // flip vertical
+					2 => (true, false), // flip horizontal
+					_ => (false, false),
+				},
+			)
+		} else {
+			(0, (false, false))
+		};
+
+		(
+			(maptile.texture_index.unwrap_or(i).abs() % textures.len() as i32) as usize,
+			quarter_turns,
+			flip,
+		)
+	} else {
+		let liquid = tile_type.liquid();
+
+		let index = if liquid.sprite_override {
+			textures.len() - 1
+		} else {
+			((textures.len() as f32 - 1.0) * (liquid.level as f32 / u8::MAX as f32)) as usize
+		};
+
+		(index, 0, (false, false))
+	};
+
+	match images.get(&textures[index]) {
+		Some(image) => {
+			blit(layer, key, image, quarter_turns, flip);
+			true
+		}
+		None => false,
+	}
+}
+
+fn draw_tile_outline(
+	layer: &mut [u8],
+	key: (u8, u8),
+	maptile: &MapTile,
+	sprites: &Sprites,
+	images: &Assets<Image>,
+) -> bool {
+	let texture = match maptile
+		.outline_id
+		.checked_sub(1)
+		.and_then(|i| sprites.tile_outlines.get(i))
+	{
+		Some(v) => v,
+		None => {
+			clear_block(layer, key);
+			return true;
+		}
+	};
+
+	match images.get(texture) {
+		Some(image) => {
+			blit(layer, key, image, 0, (false, false));
+			true
+		}
+		None => false,
+	}
+}
+
+/// Copies `image` into the block of tile `key`, rotated counterclockwise by `quarter_turns`
+/// after flipping it, like a sprite with that transform would be drawn.
+fn blit(layer: &mut [u8], key: (u8, u8), image: &Image, quarter_turns: i32, flip: (bool, bool)) {
+	let (w, h) = (TILE_SIZE.x as i32, TILE_SIZE.y as i32);
+	let source_size = image.size();
+
+	for py in 0..h {
+		for px in 0..w {
+			// doubled coordinates centered on the tile, with y pointing up
+			let (mut u, mut v) = (2 * px - (w - 1), (h - 1) - 2 * py);
+
+			for _ in 0..(4 - quarter_turns) % 4 {
+				(u, v) = (-v, u);
+			}
+
+			if flip.0 {
+				u = -u;
+			}
+
+			if flip.1 {
+				v = -v;
+			}
+
+			let sx = ((u + w - 1) / 2) as u32 * source_size.x / w as u32;
+			let sy = ((h - 1 - v) / 2) as u32 * source_size.y / h as u32;
+			let source = pixel_index(source_size.x, sx, sy);
+			let (x, y) = block_origin(key);
+			let target = pixel_index(
+				CHUNK_SIZE.0 as u32 * TILE_SIZE.x,
+				x + px as u32,
+				y + py as u32,
+			);
+
+			layer[target..target + BYTES_PER_PIXEL]
+				.copy_from_slice(&image.data[source..source + BYTES_PER_PIXEL]);
+		}
+	}
+}
+
+fn clear_block(layer: &mut [u8], key: (u8, u8)) {
+	let (x, y) = block_origin(key);
+
+	for py in 0..TILE_SIZE.y {
+		let start = pixel_index(CHUNK_SIZE.0 as u32 * TILE_SIZE.x, x, y + py);
+		layer[start..start + TILE_SIZE.x as usize * BYTES_PER_PIXEL].fill(0);
+	}
+}
+
+/// Top left pixel of a tile's block. Image rows go downwards, chunklocal y goes upwards.
+fn block_origin(key: (u8, u8)) -> (u32, u32) {
+	(
+		key.0 as u32 * TILE_SIZE.x,
+		(CHUNK_SIZE.1 - 1 - key.1) as u32 * TILE_SIZE.y,
+	)
+}
+
+fn pixel_index(width: u32, x: u32, y: u32) -> usize {
+	(y * width + x) as usize * BYTES_PER_PIXEL
+}
+
+fn layer_image(size: UVec2) -> Image {
+	Image::new_fill(
+		Extent3d {
+			width: size.x,
+			height: size.y,
+			depth_or_array_layers: 1,
+		},
+		TextureDimension::D2,
+		&[0, 0, 0, 0],
+		TextureFormat::Rgba8UnormSrgb,
+		RenderAssetUsages::default(),
+	)
+}
+
+fn take_image_data(images: &mut Assets<Image>, handle: &Handle<Image>) -> Vec<u8> {
+	match images.get_mut(handle) {
+		Some(image) => std::mem::take(&mut image.data),
+		None => vec![],
+	}
+}
+
+fn put_image_data(images: &mut Assets<Image>, handle: &Handle<Image>, data: Vec<u8>) {
+	if let Some(image) = images.get_mut(handle) {
+		image.data = data;
+	}
+}
@@ -1,16 +1,13 @@
 use crate::{
 	grid::{Coordinate, CreateTileEvent, DestroyTileEvent, Map},
-	//light::Emitter,
 	playerphysics::Position,
 	players::Player,
 	sprites::Sprites,
 	startup,
-	tilephysics::UpdateTileEvent,
-	tiletypes::{Liquid, TileType},
-	MainCamera,
-	UIWrapper,
-	WorldCursor,
-	CAMERA_PROJECTION_SCALE,
+	tilephysics::{SimulationStats, UpdateTileEvent},
+	tiletypes::registry,
+	worldgen::WorldGenerator,
+	MainCamera, UIWrapper, WorldCursor, CAMERA_PROJECTION_SCALE,
 };
 use bevy::{
 	input::mouse::MouseWheel,
@@ -88,6 +85,20 @@ fn toggle_debug_ui_event(
 	}
 }
 
+const DIGIT_KEYS: [KeyCode; 10] = [
+	KeyCode::Digit0,
+	KeyCode::Digit1,
+	KeyCode::Digit2,
+	KeyCode::Digit3,
+	KeyCode::Digit4,
+	KeyCode::Digit5,
+	KeyCode::Digit6,
+	KeyCode::Digit7,
+	KeyCode::Digit8,
+	KeyCode::Digit9,
+];
+
+/// Places the tile whose `hotkey` in the tile definitions matches the pressed digit.
 fn place_tiles(
 	q_cursor: Query<&Transform, With<WorldCursor>>,
 	mut ev_destroytile: EventWriter<DestroyTileEvent>,
@@ -99,40 +110,13 @@ fn place_tiles(
 	if let Ok(cursor_pos) = q_cursor.get_single() {
 		let world_coord = Coordinate::world_coord_from_vec2(cursor_pos.translation.truncate());
 
-		if kb_input.pressed(KeyCode::Digit0) {
-			ev_createtile.send(CreateTileEvent::new(world_coord, TileType::Empty, None));
-		} else if kb_input.pressed(KeyCode::Digit1) {
-			ev_createtile.send(CreateTileEvent::new(world_coord, TileType::Sand, None));
-		} else if kb_input.pressed(KeyCode::Digit2) {
-			ev_createtile.send(CreateTileEvent::new(world_coord, TileType::Dirt, None));
-		} else if kb_input.pressed(KeyCode::Digit3) {
-			ev_createtile.send(CreateTileEvent::new(world_coord, TileType::Gravel, None));
-		} else if kb_input.pressed(KeyCode::Digit4) {
-			ev_createtile.send(CreateTileEvent::new(world_coord, TileType::Moss, None));
-		} else if kb_input.pressed(KeyCode::Digit5) {
-			ev_createtile.send(CreateTileEvent::new(
-				world_coord,
-				TileType::Water(Liquid::default()),
-				None,
-			));
-		} else if kb_input.pressed(KeyCode::Digit6) {
-			ev_createtile.send(CreateTileEvent::new(
-				world_coord,
-				TileType::Magma(Liquid::default()),
-				None,
-			));
-		} else if kb_input.pressed(KeyCode::Digit7) {
-			ev_createtile.send(CreateTileEvent::new(
-				world_coord,
-				TileType::Oil(Liquid::default()),
-				None,
-			));
-		} else if kb_input.just_pressed(KeyCode::Digit8) {
-			//ev_createtile.send(CreateTileEvent::new(
-			//	world_coord,
-			//	TileType::Lantern(Emitter::default()),
-			//	None,
-			//));
+		for (digit, key) in DIGIT_KEYS.iter().enumerate() {
+			if kb_input.pressed(*key) {
+				if let Some(tile_type) = registry().by_hotkey(digit as u8) {
+					ev_createtile.send(CreateTileEvent::new(world_coord, tile_type, None));
+				}
+				break;
+			}
 		}
 
 		let size = if m_input.pressed(MouseButton::Left) {
@@ -229,9 +213,11 @@ fn update_info(
 	q_player: Query<(&Player, &Position)>,
 	q_cursor: Query<&Transform, With<WorldCursor>>,
 	map: Res<Map>,
+	generator: Res<WorldGenerator>,
 	time: Res<Time>,
 	mut framerate: ResMut<FrameRate>,
 	state: Res<DebugStates>,
+	stats: Res<SimulationStats>,
 ) {
 	if !state.debug_ui_enabled {
 		return;
@@ -316,7 +302,7 @@ fn update_info(
 				} else {
 					"null".to_owned()
 				},
-				t.light_level.to_string(),
+				format!("{:?}", t.light),
 				t.outline_id.to_string(),
 			)
 		} else {
@@ -336,6 +322,11 @@ fn update_info(
 		let info = format!(
 			"
 			FPS: {:.0}
+			seed: {}
+			\n
+			simulated tiles: {}\n
+			 chunks  awake: {}\n
+			       sleeping: {}\n
 			\n
 			player pos   tile: ({},{})\n
 			            world: ({},{})\n
@@ -346,6 +337,7 @@ fn update_info(
 			            world: ({},{})\n
 			            chunk: ({},{})\n
 			       chunklocal: ({},{})\n
+			            biome: {}\n
 			\n
 			cursor tile  name: {}\n
 			         weighted: {}\n
@@ -357,6 +349,10 @@ fn update_info(
 			      light level: {}\n
 			      outline id: {}",
 			framerate.avg_frame_rate,
+			generator.seed(),
+			stats.simulated_tiles,
+			stats.awake_chunks,
+			stats.sleeping_chunks,
 			player_pos.as_tile_coord().x_i32(),
 			player_pos.as_tile_coord().y_i32(),
 			player_pos.x_i32(),
@@ -373,6 +369,10 @@ fn update_info(
 			cursor_pos.as_chunk_coord().y_i32(),
 			cursor_pos.as_chunklocal_coord().x_i32(),
 			cursor_pos.as_chunklocal_coord().y_i32(),
+			generator.biome_at(
+				cursor_pos.as_tile_coord().x_i32(),
+				cursor_pos.as_tile_coord().y_i32()
+			),
 			ct_name,
 			ct_weighted,
 			ct_granularity,
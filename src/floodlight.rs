@@ -0,0 +1,234 @@
+//! Flood fill lighting, the `LightBackend::Flood` alternative to the rays of `light`.
+//!
+//! Every tile's light is kept in the chunk, and light spreads from a tile to its four neighbors,
+//! losing `LIGHT_FALLOFF` per step and whatever the tile's opacity stops. Changes are applied
+//! incrementally: light is first removed from the tiles that may have gotten it from a changed
+//! tile, then the remaining light floods back into them.
+//!
+//! The red, green and blue channels are flooded separately, so overlapping colors mix, but a
+//! tile only keeps the brightest light reaching it in each channel instead of their sum. A sum
+//! can't be kept up incrementally: removing light needs to know how much of a tile's light came
+//! through a changed tile, which a summed level no longer tells apart. Summing would mean
+//! flooding every source on its own again over its whole radius whenever a tile in it changes,
+//! which is what the rays of `light` already do.
+
+use crate::{
+	grid::{Coordinate, Map},
+	light::{AddLightSourceEvent, Emitter, LightingUpdateEvent},
+	CHUNK_SIZE,
+};
+use bevy::{
+	prelude::{EventReader, Local, ResMut},
+	utils::HashSet,
+};
+use std::collections::VecDeque;
+
+/// Light lost with every tile travelled, so that an emitter lights up to `radius` tiles away.
+const LIGHT_FALLOFF: u8 = 12;
+
+pub fn flood_lighting_event(
+	mut map: ResMut<Map>,
+	mut ev_add: EventReader<AddLightSourceEvent>,
+	mut ev_update: EventReader<LightingUpdateEvent>,
+	mut loaded_chunks: Local<HashSet<(i32, i32)>>,
+) {
+	let mut queues = LightQueues::default();
+
+	// light coming from unloaded chunks is removed, and light flows into newly loaded ones
+	let chunks = map.keys().copied().collect::<HashSet<_>>();
+
+	for chunk_pos in loaded_chunks.difference(&chunks) {
+		for c in border_tiles(*chunk_pos) {
+			queues.remove(&mut map, c);
+		}
+	}
+
+	for chunk_pos in chunks.difference(&loaded_chunks) {
+		for c in border_tiles(*chunk_pos) {
+			queues.add.extend((0..3).map(|i| (c, i)));
+		}
+	}
+
+	*loaded_chunks = chunks;
+
+	for ev in ev_update.read() {
+		queues.remove(&mut map, ev.0);
+	}
+
+	for ev in ev_add.read() {
+		// the emitter may have been replaced again in the same frame
+		let emitter = match map
+			.get_tile(ev.0.tile_coord)
+			.map(|t| t.tile_type.get_emitter())
+		{
+			Some(Ok(v)) if ev.0.tile_type.get_emitter() == Ok(v) => v,
+			_ => continue,
+		};
+
+		queues.emit(&mut map, ev.0.tile_coord, emitter);
+	}
+
+	queues.propagate(&mut map);
+}
+
+/// Tiles whose light is being removed or spread, processed breadth first.
+#[derive(Default)]
+struct LightQueues {
+	/// Darkened tiles and channels, with the light level they had.
+	remove: VecDeque<(Coordinate, usize, u8)>,
+	/// Tiles and channels whose light spreads to their neighbors.
+	add: VecDeque<(Coordinate, usize)>,
+}
+
+impl LightQueues {
+	/// Darkens `coord`, and later everything that may have been lit through it.
+	fn remove(&mut self, map: &mut Map, coord: Coordinate) {
+		let light = match map.get_tile(coord) {
+			Some(v) => v.light,
+			None => return,
+		};
+
+		for (i, light_level) in light.into_iter().enumerate() {
+			set_light_level(map, coord, i, 0);
+			self.remove.push_back((coord, i, light_level));
+		}
+	}
+
+	/// Lights `coord` as an emitter, and later everything around it.
+	fn emit(&mut self, map: &mut Map, coord: Coordinate, emitter: Emitter) {
+		let light = match map.get_tile(coord) {
+			Some(v) => v.light,
+			None => return,
+		};
+
+		let emitted = emitter_light_level(emitter);
+
+		for (i, c) in emitter.rgb().into_iter().enumerate() {
+			let light_level = (emitted as u16 * c as u16 / u8::MAX as u16) as u8;
+
+			set_light_level(map, coord, i, light[i].max(light_level));
+			self.add.push_back((coord, i));
+		}
+	}
+
+	/// Empties both queues, removing light first and then spreading what is left.
+	fn propagate(&mut self, map: &mut Map) {
+		while let Some((coord, i, light_level)) = self.remove.pop_front() {
+			for n in neighbors(coord) {
+				let t = match map.get_tile(n) {
+					Some(v) => v,
+					None => continue,
+				};
+
+				if t.light[i] == 0 {
+					continue;
+				}
+
+				// dimmer tiles may have been lit through this one, brighter ones were not and
+				// light the darkened tiles back up
+				if t.light[i] < light_level && !t.tile_type.is_emitter() {
+					set_light_level(map, n, i, 0);
+					self.remove.push_back((n, i, t.light[i]));
+				} else {
+					self.add.push_back((n, i));
+				}
+			}
+		}
+
+		while let Some((coord, i)) = self.add.pop_front() {
+			let t = match map.get_tile(coord) {
+				Some(v) => v,
+				None => continue,
+			};
+
+			let transmitted = if t.tile_type.is_emitter() {
+				t.light[i]
+			} else {
+				let opacity = t.tile_type.get_opacity() as u16;
+				(t.light[i] as u16 * (u8::MAX as u16 - opacity) / u8::MAX as u16) as u8
+			};
+
+			let light_level = transmitted.saturating_sub(LIGHT_FALLOFF);
+
+			if light_level == 0 {
+				continue;
+			}
+
+			for n in neighbors(coord) {
+				match map.get_tile(n) {
+					Some(v) if v.light[i] < light_level => {}
+					_ => continue,
+				}
+
+				set_light_level(map, n, i, light_level);
+				self.add.push_back((n, i));
+			}
+		}
+	}
+}
+
+fn emitter_light_level(emitter: Emitter) -> u8 {
+	(emitter.radius as u16 * LIGHT_FALLOFF as u16).min(u8::MAX as u16) as u8
+}
+
+/// Sets channel `i` of the light at `coord`.
+fn set_light_level(map: &mut Map, coord: Coordinate, i: usize, light_level: u8) {
+	let chunk = match map.get_chunk_mut(coord) {
+		Some(v) => v,
+		None => return,
+	};
+	let chunklocal_coord = coord.as_chunklocal_coord();
+	let t = chunk.tile_mut(chunklocal_coord.x_u8(), chunklocal_coord.y_u8());
+
+	if t.light[i] == light_level && t.lit {
+		return;
+	}
+
+	t.light[i] = light_level;
+	t.lit = true;
+	chunk.mark_redraw(coord);
+}
+
+fn neighbors(coord: Coordinate) -> [Coordinate; 4] {
+	let (x, y) = (coord.x_i32(), coord.y_i32());
+
+	[
+		Coordinate::Tile { x: x - 1, y },
+		Coordinate::Tile { x: x + 1, y },
+		Coordinate::Tile { x, y: y - 1 },
+		Coordinate::Tile { x, y: y + 1 },
+	]
+}
+
+/// Tiles of the neighboring chunks touching the edges of the chunk at `chunk_pos`.
+fn border_tiles(chunk_pos: (i32, i32)) -> impl Iterator<Item = Coordinate> {
+	let (w, h) = (CHUNK_SIZE.0 as i32, CHUNK_SIZE.1 as i32);
+	let (x0, y0) = (chunk_pos.0 * w, chunk_pos.1 * h);
+
+	let rows = (0..w).flat_map(move |x| {
+		[
+			Coordinate::Tile {
+				x: x0 + x,
+				y: y0 - 1,
+			},
+			Coordinate::Tile {
+				x: x0 + x,
+				y: y0 + h,
+			},
+		]
+	});
+	let columns = (0..h).flat_map(move |y| {
+		[
+			Coordinate::Tile {
+				x: x0 - 1,
+				y: y0 + y,
+			},
+			Coordinate::Tile {
+				x: x0 + w,
+				y: y0 + y,
+			},
+		]
+	});
+
+	rows.chain(columns)
+}
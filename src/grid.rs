@@ -1,21 +1,23 @@
 use crate::{
 	light::{AddLightSourceEvent, LightingUpdateEvent},
-	playerphysics::{Collider, Position},
-	players::Player,
-	sprites::Sprites,
+	persistence::ChunkStorage,
+	playerphysics::Position,
+	structures::StructureWrites,
 	tilephysics::UpdateTileEvent,
 	tiles::{set_tile, set_tile_result},
 	tiletypes::TileType,
-	worldgen::tiletype_at,
-	CHUNK_SIZE, RENDER_DISTANCE, TILE_SIZE, UNRENDER_DISTANCE,
+	worldgen::{chunk_index, WorldGenerator},
+	CHUNK_LOAD_RADIUS, CHUNK_MATERIALIZE_BUDGET, CHUNK_RENDER_RADIUS, CHUNK_SIZE,
+	CHUNK_UNLOAD_MARGIN, TILE_SIZE,
 };
 use bevy::{
 	prelude::{
-		App, BuildChildren, Children, Commands, Component, Deref, DerefMut, DespawnRecursiveExt,
-		Entity, Event, EventReader, EventWriter, IVec2, IntoSystemConfigs, Plugin, Query, Res,
-		ResMut, Resource, Transform, TransformBundle, Update, Vec2, Vec3, VisibilityBundle, With,
+		App, Commands, Component, Deref, DerefMut, DespawnRecursiveExt, Entity, Event, EventReader,
+		EventWriter, IVec2, IntoSystemConfigs, Plugin, Query, Res, ResMut, Resource, Transform,
+		TransformBundle, Update, Vec2, Vec3, VisibilityBundle,
 	},
-	utils::hashbrown::HashMap,
+	tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
+	utils::hashbrown::{HashMap, HashSet},
 };
 use bresenham::Bresenham;
 
@@ -24,19 +26,26 @@ pub struct Grid;
 impl Plugin for Grid {
 	fn build(&self, app: &mut App) {
 		app.insert_resource(Map(HashMap::new()))
+			.init_resource::<StructureWrites>()
+			.init_resource::<PendingChunks>()
 			.add_event::<DestroyTileEvent>()
 			.add_event::<CreateTileEvent>()
 			.add_systems(
 				Update,
-				(render_chunks, destroy_tile_event, create_tile_event).chain(),
+				(
+					render_chunks,
+					materialize_chunks,
+					destroy_tile_event,
+					create_tile_event,
+				)
+					.chain(),
 			);
 	}
 }
 
 #[derive(Component)]
-pub struct Chunk(IVec2);
+pub struct Chunk(pub IVec2);
 
-#[derive(Component)]
 pub struct Region {
 	pub top: f32,
 	pub left: f32,
@@ -62,58 +71,324 @@ impl Region {
 			right: self.right + movement.x,
 		}
 	}
+
+	pub fn expanded(&self, amount: &Vec2) -> Self {
+		Self {
+			top: self.top + amount.y,
+			bottom: self.bottom - amount.y,
+			left: self.left - amount.x,
+			right: self.right + amount.x,
+		}
+	}
 }
 
-#[derive(Resource, Deref, DerefMut)]
+#[derive(Resource, Deref, DerefMut, Default)]
 pub struct Map(HashMap<(i32, i32), MapChunk>);
 
 impl Map {
 	pub fn get_tile(&self, coord: Coordinate) -> Option<MapTile> {
-		let chunk_coord = coord.as_chunk_coord();
-		let chunklocal_coord = coord.as_chunklocal_coord();
+		let (chunk_pos, (x, y)) = split_tile_coord(coord);
+		self.0.get(&chunk_pos).map(|chunk| *chunk.tile(x, y))
+	}
 
-		if let Some(chunk) = self.0.get(&(chunk_coord.x_i32(), chunk_coord.y_i32())) {
-			if let Some(tile) = chunk
-				.tiles
-				.get(&(chunklocal_coord.x_u8(), chunklocal_coord.y_u8()))
-			{
-				return Some(*tile);
-			}
+	pub fn get_tile_mut(&mut self, coord: Coordinate) -> Option<&mut MapTile> {
+		let (chunk_pos, (x, y)) = split_tile_coord(coord);
+		self.0.get_mut(&chunk_pos).map(|chunk| chunk.tile_mut(x, y))
+	}
+
+	pub fn get_chunk_mut(&mut self, coord: Coordinate) -> Option<&mut MapChunk> {
+		let (chunk_pos, _) = split_tile_coord(coord);
+		self.0.get_mut(&chunk_pos)
+	}
+
+	pub fn cursor(&self) -> TileCursor<'_> {
+		TileCursor {
+			map: self,
+			chunk: None,
 		}
+	}
 
-		None
+	/// Tiles starting at `from` and repeatedly moving by `step`, e.g. `IVec2::X` to walk a row
+	/// to the right or `IVec2::NEG_Y` to walk a column downwards. The iterator never ends.
+	pub fn line(
+		&self,
+		from: Coordinate,
+		step: IVec2,
+	) -> impl Iterator<Item = Option<MapTile>> + '_ {
+		let mut cursor = self.cursor();
+		let mut coord = from.as_tile_coord();
+
+		std::iter::from_fn(move || {
+			let tile = cursor.get(coord);
+			coord = coord.moved(&step.as_vec2());
+			Some(tile)
+		})
 	}
 
-	pub fn get_tile_mut(&mut self, coord: Coordinate) -> Option<&mut MapTile> {
-		let chunk_coord = coord.as_chunk_coord();
-		let chunklocal_coord = coord.as_chunklocal_coord();
+	/// Tiles in the rectangle from `min` to `max` inclusive, row by row from the bottom.
+	pub fn region(&self, min: IVec2, max: IVec2) -> impl Iterator<Item = Option<MapTile>> + '_ {
+		let mut cursor = self.cursor();
 
-		if let Some(chunk) = self.0.get_mut(&(chunk_coord.x_i32(), chunk_coord.y_i32())) {
-			if let Some(tile) = chunk
-				.tiles
-				.get_mut(&(chunklocal_coord.x_u8(), chunklocal_coord.y_u8()))
-			{
-				return Some(tile);
+		(min.y..=max.y)
+			.flat_map(move |y| (min.x..=max.x).map(move |x| Coordinate::Tile { x, y }))
+			.map(move |coord| cursor.get(coord))
+	}
+}
+
+/// Looks tiles up while remembering the last chunk it visited, so that scanning neighboring
+/// tiles only hashes a chunk position when the scan crosses into another chunk.
+pub struct TileCursor<'a> {
+	map: &'a Map,
+	chunk: Option<((i32, i32), Option<&'a MapChunk>)>,
+}
+
+impl TileCursor<'_> {
+	pub fn get(&mut self, coord: Coordinate) -> Option<MapTile> {
+		let (chunk_pos, (x, y)) = split_tile_coord(coord);
+
+		let chunk = match self.chunk {
+			Some((pos, chunk)) if pos == chunk_pos => chunk,
+			_ => {
+				let chunk = self.map.0.get(&chunk_pos);
+				self.chunk = Some((chunk_pos, chunk));
+				chunk
 			}
-		}
+		};
 
-		None
+		chunk.map(|chunk| *chunk.tile(x, y))
 	}
 }
 
+/// Chunk position and chunklocal position of a coordinate.
+fn split_tile_coord(coord: Coordinate) -> ((i32, i32), (u8, u8)) {
+	let tile_coord = coord.as_tile_coord();
+	let (x, y) = (tile_coord.x_i32(), tile_coord.y_i32());
+	let (w, h) = (CHUNK_SIZE.0 as i32, CHUNK_SIZE.1 as i32);
+
+	(
+		(x.div_euclid(w), y.div_euclid(h)),
+		(x.rem_euclid(w) as u8, y.rem_euclid(h) as u8),
+	)
+}
+
+const CHUNK_TILE_COUNT: usize = CHUNK_SIZE.0 as usize * CHUNK_SIZE.1 as usize;
+
 pub struct MapChunk {
 	pub entity: Entity,
-	pub tiles: HashMap<(u8, u8), MapTile>,
+	tiles: Box<[MapTile; CHUNK_TILE_COUNT]>,
+	pub modified: bool,
+	/// Weighted tiles that may be able to fall, see `tilephysics::apply_gravity`.
+	pub falling: ActiveTiles,
+	/// Liquid tiles that may be able to flow, see `tilephysics::flow_liquid_tile`.
+	pub flowing: ActiveTiles,
+	/// Gases and other tiles with a lifetime, see `tilephysics::simulate_gases`.
+	pub decaying: ActiveTiles,
+	/// Tiles whose appearance changed since the chunk was last drawn.
+	pub redraw: HashSet<(u8, u8)>,
+	pub tier: ChunkTier,
+}
+
+impl MapChunk {
+	/// `tiles` must hold every tile of the chunk in `chunk_index` order.
+	pub fn new(entity: Entity, tiles: Vec<MapTile>) -> Self {
+		Self {
+			entity,
+			tiles: tiles
+				.into_boxed_slice()
+				.try_into()
+				.unwrap_or_else(|_| panic!("A chunk needs exactly {CHUNK_TILE_COUNT} tiles")),
+			modified: false,
+			falling: ActiveTiles::default(),
+			flowing: ActiveTiles::default(),
+			decaying: ActiveTiles::default(),
+			redraw: HashSet::new(),
+			tier: ChunkTier::Frozen,
+		}
+	}
+
+	pub fn tile(&self, x: u8, y: u8) -> &MapTile {
+		&self.tiles[chunk_index(x as i32, y as i32)]
+	}
+
+	pub fn tile_mut(&mut self, x: u8, y: u8) -> &mut MapTile {
+		&mut self.tiles[chunk_index(x as i32, y as i32)]
+	}
+
+	/// Every tile with its chunklocal position, in `chunk_index` order.
+	pub fn iter(&self) -> impl Iterator<Item = ((u8, u8), &MapTile)> {
+		(0..CHUNK_SIZE.0)
+			.flat_map(|x| (0..CHUNK_SIZE.1).map(move |y| (x, y)))
+			.zip(self.tiles.iter())
+	}
+
+	pub fn redraw_all(&mut self) {
+		self.redraw
+			.extend((0..CHUNK_SIZE.0).flat_map(|x| (0..CHUNK_SIZE.1).map(move |y| (x, y))));
+	}
+
+	pub fn mark_redraw(&mut self, coord: Coordinate) {
+		let (_, key) = split_tile_coord(coord);
+		self.redraw.insert(key);
+	}
+
+	/// Whether the tile simulation has nothing to do in this chunk. Sleeping chunks are skipped
+	/// without looking at their tiles, and wake up as soon as a tile in them is activated.
+	pub fn is_asleep(&self) -> bool {
+		self.falling.is_empty() && self.flowing.is_empty() && self.decaying.is_empty()
+	}
+}
+
+// every row of a chunk has to fit in one `ActiveTiles` bitmask
+const _: () = assert!(CHUNK_SIZE.0 as u32 <= u32::BITS);
+
+/// Set of chunklocal tiles that need to be simulated, stored as one bitmask per row together
+/// with the dirty rectangle around the set bits. Scanning the set only visits the rectangle, and
+/// yields the tiles in a fixed order so the simulation doesn't have to sort them.
+#[derive(Default)]
+pub struct ActiveTiles {
+	rows: [u32; CHUNK_SIZE.1 as usize],
+	/// Inclusive bounds of the set tiles, may be larger than needed until the next scan.
+	rect: Option<((u8, u8), (u8, u8))>,
+	len: usize,
+}
+
+#[derive(Clone, Copy)]
+pub enum ScanOrder {
+	/// Bottom row first, every row from left to right.
+	Rows,
+	/// Top row first, every row from left to right.
+	RowsDownward,
+	/// Every column from the bottom up, columns from left to right or the other way around.
+	Columns { right_to_left: bool },
+}
+
+impl ActiveTiles {
+	pub fn insert(&mut self, key: (u8, u8)) -> bool {
+		let bit = 1 << key.0;
+
+		if self.rows[key.1 as usize] & bit != 0 {
+			return false;
+		}
+
+		self.rows[key.1 as usize] |= bit;
+		self.len += 1;
+		self.rect = Some(match self.rect {
+			Some((min, max)) => (
+				(min.0.min(key.0), min.1.min(key.1)),
+				(max.0.max(key.0), max.1.max(key.1)),
+			),
+			None => (key, key),
+		});
+
+		true
+	}
+
+	pub fn remove(&mut self, key: (u8, u8)) -> bool {
+		let bit = 1 << key.0;
+
+		if self.rows[key.1 as usize] & bit == 0 {
+			return false;
+		}
+
+		self.rows[key.1 as usize] &= !bit;
+		self.len -= 1;
+
+		if self.len == 0 {
+			self.rect = None;
+		}
+
+		true
+	}
+
+	pub fn contains(&self, key: (u8, u8)) -> bool {
+		self.rows[key.1 as usize] & (1 << key.0) != 0
+	}
+
+	pub fn is_empty(&self) -> bool {
+		self.len == 0
+	}
+
+	/// Every tile in the set, in `order`. The dirty rectangle is shrunk to fit the set first.
+	pub fn scan(&mut self, order: ScanOrder) -> Vec<(u8, u8)> {
+		let ((x_min, y_min), (x_max, y_max)) = match self.shrink() {
+			Some(v) => v,
+			None => return vec![],
+		};
+
+		let mut keys = Vec::with_capacity(self.len);
+
+		match order {
+			ScanOrder::Rows | ScanOrder::RowsDownward => {
+				let mut rows = (y_min..=y_max).collect::<Vec<_>>();
+
+				if let ScanOrder::RowsDownward = order {
+					rows.reverse();
+				}
+
+				for y in rows {
+					let mut row = self.rows[y as usize];
+
+					while row != 0 {
+						keys.push((row.trailing_zeros() as u8, y));
+						row &= row - 1;
+					}
+				}
+			}
+			ScanOrder::Columns { right_to_left } => {
+				let mut columns = (x_min..=x_max).collect::<Vec<_>>();
+
+				if right_to_left {
+					columns.reverse();
+				}
+
+				for x in columns {
+					for y in y_min..=y_max {
+						if self.contains((x, y)) {
+							keys.push((x, y));
+						}
+					}
+				}
+			}
+		}
+
+		keys
+	}
+
+	fn shrink(&mut self) -> Option<((u8, u8), (u8, u8))> {
+		let ((_, y_min), (_, y_max)) = self.rect?;
+		let mut rect: Option<((u8, u8), (u8, u8))> = None;
+
+		for y in y_min..=y_max {
+			let row = self.rows[y as usize];
+
+			if row == 0 {
+				continue;
+			}
+
+			let (left, right) = (row.trailing_zeros() as u8, (31 - row.leading_zeros()) as u8);
+
+			rect = Some(match rect {
+				Some((min, max)) => ((min.0.min(left), min.1), (max.0.max(right), y)),
+				None => ((left, y), (right, y)),
+			});
+		}
+
+		self.rect = rect;
+		rect
+	}
 }
 
 #[derive(Clone, Copy)]
 pub struct MapTile {
-	pub tile_entity: Entity,
-	pub outline_entity: Entity,
-	pub sprite_entity: Entity,
-	pub light_entity: Entity,
-	pub light_level: u8,
+	/// Light reaching the tile in each of the red, green and blue channels.
+	pub light: [u8; 3],
+	/// Light the sky gives the tile at noon, see `sky`.
+	pub sky_light: u8,
+	/// Whether lighting has reached this tile. Unlit tiles are drawn without a light overlay.
+	pub lit: bool,
 	pub outline_id: usize,
+	/// Overrides the sprite variant picked from the tile's coordinate, see `tiles::set_tile`.
+	pub texture_index: Option<i32>,
 	pub tile_type: TileType,
 	pub tile_coord: Coordinate,
 }
@@ -320,44 +595,151 @@ impl PartialEq for Coordinate {
 	}
 }
 
+/// Tile types of a chunk, in `chunk_index` order, before they are added to the map.
+pub struct ChunkTiles {
+	tile_types: Vec<TileType>,
+	/// Whether the tiles were read from the chunk's save rather than generated.
+	loaded: bool,
+}
+
+impl ChunkTiles {
+	/// Reads the chunk's save, or generates its terrain if there is none. Structures are only
+	/// applied once the chunk is added to the map, since they depend on shared state.
+	pub fn load_or_generate(
+		chunk_pos: IVec2,
+		storage: &ChunkStorage,
+		generator: &WorldGenerator,
+	) -> Self {
+		match storage.load_chunk(chunk_pos) {
+			Some(tile_types) => Self {
+				tile_types,
+				loaded: true,
+			},
+			None => Self {
+				tile_types: generator.generate_chunk(chunk_pos),
+				loaded: false,
+			},
+		}
+	}
+
+	/// A saved chunk holding only `tile_type`.
+	#[cfg(test)]
+	pub fn filled(tile_type: TileType) -> Self {
+		Self {
+			tile_types: vec![tile_type; CHUNK_TILE_COUNT],
+			loaded: true,
+		}
+	}
+}
+
+/// Keeps the chunks within `radius` chunks of its entity loaded, in both directions. The outer
+/// ring of that area is frozen so that every simulated chunk has loaded neighbors, the chunks
+/// inside it are simulated, and the ones within `render_radius` are drawn as well. A chunk stays
+/// loaded while any loader is in range, and is unloaded once it is more than
+/// `CHUNK_UNLOAD_MARGIN` chunks outside of every loader's radius.
+#[derive(Component)]
+pub struct ChunkLoader {
+	pub radius: u32,
+	pub render_radius: u32,
+}
+
+impl Default for ChunkLoader {
+	fn default() -> Self {
+		Self {
+			radius: CHUNK_LOAD_RADIUS,
+			render_radius: CHUNK_RENDER_RADIUS,
+		}
+	}
+}
+
+impl ChunkLoader {
+	/// Tier the loader keeps a chunk in, or None if it doesn't keep the chunk loaded.
+	fn tier(&self, center: IVec2, chunk_pos: IVec2) -> Option<ChunkTier> {
+		let distance = (chunk_pos - center).abs();
+		let distance = distance.x.max(distance.y) as u32;
+
+		if distance > self.radius + CHUNK_UNLOAD_MARGIN {
+			None
+		} else if distance >= self.radius {
+			Some(ChunkTier::Frozen)
+		} else if distance <= self.render_radius {
+			Some(ChunkTier::Rendered)
+		} else {
+			Some(ChunkTier::Simulated)
+		}
+	}
+}
+
+/// How much of the game runs in a loaded chunk, the highest tier any chunk loader keeps it in.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum ChunkTier {
+	/// Only kept in the map, so that the chunks next to it can be simulated.
+	Frozen,
+	/// Tile physics run, but the chunk isn't drawn.
+	Simulated,
+	Rendered,
+}
+
+impl ChunkTier {
+	pub fn is_simulated(&self) -> bool {
+		*self >= ChunkTier::Simulated
+	}
+}
+
+/// Chunks being loaded or generated on the `AsyncComputeTaskPool`. Dropping a task cancels it.
+#[derive(Resource, Default)]
+pub struct PendingChunks(HashMap<(i32, i32), Task<ChunkTiles>>);
+
+/// Loads or generates a chunk and adds it to the map within the current frame.
 pub fn spawn_chunk(
 	commands: &mut Commands,
 	chunk_pos: IVec2,
 	map: &mut Map,
-	sprites: &Sprites,
+	storage: &ChunkStorage,
+	generator: &WorldGenerator,
+	structures: &mut StructureWrites,
 	ev_update: &mut EventWriter<UpdateTileEvent>,
 	ev_addlightsource: &mut EventWriter<AddLightSourceEvent>,
 	ev_updatelighting: &mut EventWriter<LightingUpdateEvent>,
 ) -> Entity {
-	let tilesize_x_f32 = TILE_SIZE.x as f32;
-	let tilesize_y_f32 = TILE_SIZE.y as f32;
-
-	let transform = Transform::from_translation(Vec3::new(
-		chunk_pos.x as f32 * CHUNK_SIZE.0 as f32 * tilesize_x_f32,
-		chunk_pos.y as f32 * CHUNK_SIZE.1 as f32 * tilesize_y_f32,
-		0.0,
-	));
+	insert_chunk(
+		commands,
+		chunk_pos,
+		ChunkTiles::load_or_generate(chunk_pos, storage, generator),
+		map,
+		generator,
+		structures,
+		ev_update,
+		ev_addlightsource,
+		ev_updatelighting,
+	)
+}
 
+pub fn insert_chunk(
+	commands: &mut Commands,
+	chunk_pos: IVec2,
+	chunk_tiles: ChunkTiles,
+	map: &mut Map,
+	generator: &WorldGenerator,
+	structures: &mut StructureWrites,
+	ev_update: &mut EventWriter<UpdateTileEvent>,
+	ev_addlightsource: &mut EventWriter<AddLightSourceEvent>,
+	ev_updatelighting: &mut EventWriter<LightingUpdateEvent>,
+) -> Entity {
 	let chunk_entity = commands
 		.spawn((
 			VisibilityBundle {
 				..Default::default()
 			},
 			TransformBundle {
-				local: transform,
+				local: Transform::from_translation(Vec3::new(
+					chunk_pos.x as f32 * CHUNK_SIZE.0 as f32 * TILE_SIZE.x as f32,
+					chunk_pos.y as f32 * CHUNK_SIZE.1 as f32 * TILE_SIZE.y as f32,
+					0.0,
+				)),
 				..Default::default()
 			},
 			Chunk(chunk_pos),
-			Region::from_size(
-				&Vec2::new(
-					transform.translation.x - (tilesize_x_f32 * 0.5),
-					transform.translation.y - (tilesize_y_f32 * 0.5),
-				),
-				&Vec2::new(
-					CHUNK_SIZE.0 as f32 * tilesize_x_f32,
-					CHUNK_SIZE.1 as f32 * tilesize_y_f32,
-				),
-			),
 		))
 		.id();
 
@@ -367,90 +749,53 @@ pub fn spawn_chunk(
 		}
 	}
 
-	let mut tiles = HashMap::new();
+	let mut tiles = vec![];
 
 	for x in 0..CHUNK_SIZE.0 {
 		for y in 0..CHUNK_SIZE.1 {
-			let x_f32 = x as f32;
-			let y_f32 = y as f32;
-
-			let tile = commands
-				.spawn((
-					VisibilityBundle {
-						..Default::default()
-					},
-					TransformBundle {
-						local: Transform {
-							translation: Vec3 {
-								x: x_f32 * tilesize_x_f32,
-								y: y_f32 * tilesize_y_f32,
-								z: 0.0,
-							},
-							..Default::default()
-						},
-						..Default::default()
-					},
-					Region::from_size(
-						&Vec2::new(
-							(x_f32 * TILE_SIZE.x as f32)
-								+ (chunk_pos.x as f32 * CHUNK_SIZE.0 as f32 * TILE_SIZE.x as f32)
-								- (tilesize_x_f32 * 0.5),
-							(y_f32 * tilesize_y_f32)
-								+ (chunk_pos.y as f32 * CHUNK_SIZE.1 as f32 * TILE_SIZE.y as f32)
-								- (TILE_SIZE.y as f32 * 0.5),
-						),
-						&Vec2::new(tilesize_x_f32, tilesize_y_f32),
-					),
-				))
-				.id();
-
-			let outline = commands.spawn_empty().id();
-			let sprite = commands.spawn_empty().id();
-			let light = commands.spawn_empty().id();
-			commands.entity(tile).add_child(outline);
-			commands.entity(tile).add_child(sprite);
-			commands.entity(tile).add_child(light);
-			commands.entity(chunk_entity).add_child(tile);
-
-			tiles.insert(
-				(x, y),
-				MapTile {
-					tile_entity: tile,
-					sprite_entity: sprite,
-					outline_entity: outline,
-					light_entity: light,
-					light_level: 0,
-					outline_id: 40,
-					tile_type: TileType::Empty,
-					tile_coord: Coordinate::Tile {
-						x: (chunk_pos.x * CHUNK_SIZE.0 as i32) + x as i32,
-						y: (chunk_pos.y * CHUNK_SIZE.1 as i32) + y as i32,
-					},
+			tiles.push(MapTile {
+				light: [0; 3],
+				sky_light: 0,
+				lit: false,
+				outline_id: 40,
+				texture_index: None,
+				tile_type: TileType::EMPTY,
+				tile_coord: Coordinate::Tile {
+					x: (chunk_pos.x * CHUNK_SIZE.0 as i32) + x as i32,
+					y: (chunk_pos.y * CHUNK_SIZE.1 as i32) + y as i32,
 				},
-			);
+			});
 		}
 	}
+
 	map.0.insert(
 		(chunk_pos.x, chunk_pos.y),
-		MapChunk {
-			entity: chunk_entity,
-			tiles,
-		},
+		MapChunk::new(chunk_entity, tiles),
 	);
+
+	let mut tile_types = chunk_tiles.tile_types;
+
+	let structure_written = if chunk_tiles.loaded {
+		structures.discard(chunk_pos);
+		false
+	} else {
+		structures.apply(generator, chunk_pos, &mut tile_types)
+	};
+
+	let mut tile_types = tile_types.into_iter();
+
 	for x in 0..CHUNK_SIZE.0 {
 		for y in 0..CHUNK_SIZE.1 {
 			let tile_x = (chunk_pos.x * CHUNK_SIZE.0 as i32) + x as i32;
 			let tile_y = (chunk_pos.y * CHUNK_SIZE.1 as i32) + y as i32;
-			let tile_type = tiletype_at(tile_x, tile_y);
+			let tile_type = tile_types.next().unwrap_or(TileType::EMPTY);
 
 			if set_tile_result(
-				commands,
 				Coordinate::Tile {
 					x: tile_x,
 					y: tile_y,
 				},
 				tile_type,
-				sprites,
 				map,
 				ev_update,
 				ev_addlightsource,
@@ -464,6 +809,11 @@ pub fn spawn_chunk(
 		}
 	}
 
+	// structure writes can't be generated again, so chunks holding some are saved when unloaded
+	if let Some(chunk) = map.0.get_mut(&(chunk_pos.x, chunk_pos.y)) {
+		chunk.modified = structure_written;
+	}
+
 	for x in -1..=1 {
 		for y in -1..=1 {
 			let chunk_coord = Coordinate::Chunk { x, y }.moved(&chunk_pos.as_vec2());
@@ -505,55 +855,54 @@ pub fn spawn_chunk(
 	chunk_entity
 }
 
-pub fn despawn_chunk(commands: &mut Commands, chunk_pos: IVec2, map: &mut Map) {
+pub fn despawn_chunk(
+	commands: &mut Commands,
+	chunk_pos: IVec2,
+	map: &mut Map,
+	storage: &ChunkStorage,
+) {
 	if let Some(v) = map.0.get(&(chunk_pos.x, chunk_pos.y)) {
 		if let Some(e) = commands.get_entity(v.entity) {
 			e.despawn_recursive();
 		};
+
+		if v.modified {
+			storage.save_chunk(chunk_pos, v);
+		}
 	}
 	map.0.remove(&(chunk_pos.x, chunk_pos.y));
 }
 
-pub fn region_collides(
-	region: &Region,
-	q_colliders: &Query<&Region, With<Collider>>,
-	q_chunks: &Query<(&Region, &Children), With<Chunk>>,
-) -> bool {
-	for (chunk_region, chunk_children) in q_chunks {
-		if !regions_overlap(chunk_region, region) {
-			continue;
-		}
+/// Whether `region` overlaps any solid tile. Tiles in unloaded chunks never collide.
+pub fn region_collides(region: &Region, map: &Map) -> bool {
+	let (min, max) = region_tiles(region);
 
-		for &child in chunk_children.iter() {
-			let tile_region = match q_colliders.get(child) {
-				Ok(v) => v,
-				Err(_) => continue,
-			};
+	map.region(min, max)
+		.any(|t| t.is_some_and(|t| t.tile_type.is_solid()))
+}
 
-			if !regions_overlap(tile_region, region) {
-				continue;
-			}
+/// Whether every tile `region` overlaps is in a loaded chunk.
+pub fn region_loaded(region: &Region, map: &Map) -> bool {
+	let (min, max) = region_tiles(region);
 
-			return true;
-		}
-	}
-	false
+	map.region(min, max).all(|t| t.is_some())
 }
 
-fn regions_overlap(region_1: &Region, region_2: &Region) -> bool {
-	if region_1.right < region_2.left {
-		return false;
-	}
-	if region_1.left > region_2.right {
-		return false;
-	}
-	if region_1.top < region_2.bottom {
-		return false;
-	}
-	if region_1.bottom > region_2.top {
-		return false;
-	}
-	true
+/// First and last tile whose region touches `region`, touching edges count as overlapping.
+fn region_tiles(region: &Region) -> (IVec2, IVec2) {
+	let half_x = TILE_SIZE.x as f32 * 0.5;
+	let half_y = TILE_SIZE.y as f32 * 0.5;
+
+	(
+		IVec2::new(
+			((region.left - half_x) / TILE_SIZE.x as f32).ceil() as i32,
+			((region.bottom - half_y) / TILE_SIZE.y as f32).ceil() as i32,
+		),
+		IVec2::new(
+			((region.right + half_x) / TILE_SIZE.x as f32).floor() as i32,
+			((region.top + half_y) / TILE_SIZE.y as f32).floor() as i32,
+		),
+	)
 }
 
 fn destroy_tile_event(
@@ -562,15 +911,11 @@ fn destroy_tile_event(
 	mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
 	mut ev_updatelighting: EventWriter<LightingUpdateEvent>,
 	mut map: ResMut<Map>,
-	mut commands: Commands,
-	sprites: Res<Sprites>,
 ) {
 	for ev in ev_destroy.read() {
 		set_tile(
-			&mut commands,
 			ev.0,
-			TileType::Empty,
-			&sprites,
+			TileType::EMPTY,
 			&mut map,
 			&mut ev_update,
 			&mut ev_addlightsource,
@@ -581,62 +926,171 @@ fn destroy_tile_event(
 }
 
 pub fn render_chunks(
-	q_player: Query<(&Player, &Position)>,
+	q_loaders: Query<(&ChunkLoader, Option<&Position>, Option<&Transform>)>,
 	mut map: ResMut<Map>,
 	mut commands: Commands,
 	q_chunks: Query<&Chunk>,
-	sprites: Res<Sprites>,
+	storage: Res<ChunkStorage>,
+	generator: Res<WorldGenerator>,
+	mut structures: ResMut<StructureWrites>,
+	mut pending: ResMut<PendingChunks>,
 	mut ev_update: EventWriter<UpdateTileEvent>,
 	mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
 	mut ev_updatelighting: EventWriter<LightingUpdateEvent>,
 ) {
-	for (player, position) in q_player.iter() {
-		if let Player::Local = player {
-			//despawn
-			let player_chunk_ivec2 = Coordinate::world_coord_from_vec2(position.0).as_chunk_coord();
-			for chunk in q_chunks.iter() {
-				if (chunk.0.x - player_chunk_ivec2.x_i32()).abs() > UNRENDER_DISTANCE.x as i32
-					|| (chunk.0.y - player_chunk_ivec2.y_i32()).abs() > UNRENDER_DISTANCE.y as i32
-				{
-					despawn_chunk(&mut commands, chunk.0, &mut map);
+	let loaders = q_loaders
+		.iter()
+		.filter_map(|(loader, position, transform)| {
+			Some((loader, loader_chunk(position, transform)?))
+		})
+		.collect::<Vec<_>>();
+
+	// nothing is loaded or unloaded before the first loader is spawned
+	if loaders.is_empty() {
+		return;
+	}
+
+	let tier = |chunk_pos: IVec2| {
+		loaders
+			.iter()
+			.filter_map(|(loader, center)| loader.tier(*center, chunk_pos))
+			.max()
+	};
+
+	//despawn
+	for chunk in q_chunks.iter() {
+		match tier(chunk.0) {
+			Some(t) => {
+				if let Some(map_chunk) = map.0.get_mut(&(chunk.0.x, chunk.0.y)) {
+					map_chunk.tier = t;
 				}
 			}
+			None => despawn_chunk(&mut commands, chunk.0, &mut map, &storage),
+		}
+	}
 
-			//spawn
-			let current_chunk_coord =
-				Coordinate::world_coord_from_vec2(position.0).as_chunk_coord();
-			for x in (current_chunk_coord.x_i32() - RENDER_DISTANCE.x as i32)
-				..(current_chunk_coord.x_i32() + RENDER_DISTANCE.x as i32)
-			{
-				for y in (current_chunk_coord.y_i32() - RENDER_DISTANCE.y as i32)
-					..(current_chunk_coord.y_i32() + RENDER_DISTANCE.y as i32)
-				{
-					if map.0.contains_key(&(x, y)) {
-						continue;
-					}
-					spawn_chunk(
-						&mut commands,
-						IVec2::new(x, y),
-						&mut map,
-						&sprites,
-						&mut ev_update,
-						&mut ev_addlightsource,
-						&mut ev_updatelighting,
-					);
+	pending
+		.0
+		.retain(|chunk_pos, _| tier(IVec2::new(chunk_pos.0, chunk_pos.1)).is_some());
+
+	//spawn
+	for (loader, center) in loaders.iter() {
+		// a loader's own chunk is needed right away, e.g. when the player enters the world
+		if !map.0.contains_key(&(center.x, center.y)) {
+			pending.0.remove(&(center.x, center.y));
+
+			spawn_chunk(
+				&mut commands,
+				*center,
+				&mut map,
+				&storage,
+				&generator,
+				&mut structures,
+				&mut ev_update,
+				&mut ev_addlightsource,
+				&mut ev_updatelighting,
+			);
+		}
+
+		let radius = loader.radius as i32;
+
+		for x in (center.x - radius)..=(center.x + radius) {
+			for y in (center.y - radius)..=(center.y + radius) {
+				if map.0.contains_key(&(x, y)) || pending.0.contains_key(&(x, y)) {
+					continue;
 				}
+
+				let storage = storage.clone();
+				let generator = generator.clone();
+
+				pending.0.insert(
+					(x, y),
+					AsyncComputeTaskPool::get().spawn(async move {
+						ChunkTiles::load_or_generate(IVec2::new(x, y), &storage, &generator)
+					}),
+				);
 			}
 		}
 	}
 }
 
-pub fn create_tile_event(
+/// Chunk a loader is in. Players are located by their `Position`, anything else by its transform.
+fn loader_chunk(position: Option<&Position>, transform: Option<&Transform>) -> Option<IVec2> {
+	let position = position
+		.map(|p| p.0)
+		.or(transform.map(|t| t.translation.truncate()))?;
+	let chunk_coord = Coordinate::world_coord_from_vec2(position).as_chunk_coord();
+
+	Some(IVec2::new(chunk_coord.x_i32(), chunk_coord.y_i32()))
+}
+
+/// Adds chunks whose tiles are ready to the map, closest to a chunk loader first. At most
+/// `CHUNK_MATERIALIZE_BUDGET` chunks are added per frame, the rest wait for the next frames.
+fn materialize_chunks(
+	q_loaders: Query<(&ChunkLoader, Option<&Position>, Option<&Transform>)>,
 	mut map: ResMut<Map>,
 	mut commands: Commands,
+	generator: Res<WorldGenerator>,
+	mut structures: ResMut<StructureWrites>,
+	mut pending: ResMut<PendingChunks>,
+	mut ev_update: EventWriter<UpdateTileEvent>,
+	mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
+	mut ev_updatelighting: EventWriter<LightingUpdateEvent>,
+) {
+	let centers = q_loaders
+		.iter()
+		.filter_map(|(_, position, transform)| loader_chunk(position, transform))
+		.collect::<Vec<_>>();
+
+	let mut chunk_positions = pending.0.keys().copied().collect::<Vec<_>>();
+	chunk_positions.sort_by_key(|(x, y)| {
+		let distance = centers
+			.iter()
+			.map(|c| (IVec2::new(*x, *y) - *c).length_squared())
+			.min();
+
+		(distance, *x, *y)
+	});
+
+	let mut materialized = 0;
+
+	for chunk_pos in chunk_positions {
+		if materialized == CHUNK_MATERIALIZE_BUDGET {
+			break;
+		}
+
+		let chunk_tiles = match pending.0.get_mut(&chunk_pos) {
+			Some(task) => match block_on(poll_once(task)) {
+				Some(v) => v,
+				None => continue,
+			},
+			None => continue,
+		};
+
+		pending.0.remove(&chunk_pos);
+
+		insert_chunk(
+			&mut commands,
+			IVec2::new(chunk_pos.0, chunk_pos.1),
+			chunk_tiles,
+			&mut map,
+			&generator,
+			&mut structures,
+			&mut ev_update,
+			&mut ev_addlightsource,
+			&mut ev_updatelighting,
+		);
+
+		materialized += 1;
+	}
+}
+
+pub fn create_tile_event(
+	mut map: ResMut<Map>,
 	mut ev_create: EventReader<CreateTileEvent>,
 	mut ev_update: EventWriter<UpdateTileEvent>,
 	mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
 	mut ev_updatelighting: EventWriter<LightingUpdateEvent>,
-	sprites: Res<Sprites>,
 ) {
 	for ev in ev_create.read() {
 		if let Some(prev_maptile) = ev.prev_maptile {
@@ -650,10 +1104,8 @@ pub fn create_tile_event(
 		}
 
 		set_tile(
-			&mut commands,
 			ev.coord,
 			ev.new_tile_type,
-			&sprites,
 			&mut map,
 			&mut ev_update,
 			&mut ev_addlightsource,
@@ -697,3 +1149,457 @@ impl CreateTileEvent {
 		}
 	}
 }
+
+#[cfg(test)]
+mod tests {
+	use super::{
+		despawn_chunk, spawn_chunk, ActiveTiles, ChunkLoader, ChunkTier, Coordinate, Grid, Map,
+		MapChunk, MapTile, ScanOrder,
+	};
+	use crate::{
+		light::{AddLightSourceEvent, LightingUpdateEvent},
+		persistence::ChunkStorage,
+		playerphysics::Position,
+		structures::StructureWrites,
+		tilephysics::UpdateTileEvent,
+		tiletypes::TileType,
+		worldgen::{chunk_index, WorldGenerator, DEFAULT_SEED},
+		CHUNK_LOAD_RADIUS, CHUNK_MATERIALIZE_BUDGET, CHUNK_SIZE, TILE_SIZE,
+	};
+	use bevy::{
+		ecs::system::RunSystemOnce,
+		prelude::{
+			App, Commands, Entity, EventWriter, IVec2, MinimalPlugins, Res, ResMut, Transform, Vec2,
+		},
+		utils::hashbrown::HashMap,
+	};
+	use std::{
+		env, fs,
+		hint::black_box,
+		process, thread,
+		time::{Duration, Instant},
+	};
+
+	/// Chunk storage in an empty directory of its own.
+	fn temp_storage(name: &str) -> ChunkStorage {
+		let directory =
+			env::temp_dir().join(format!("bevy-tilegame-base-tests-{name}-{}", process::id()));
+		let _ = fs::remove_dir_all(&directory);
+
+		ChunkStorage::new(directory)
+	}
+
+	fn loader_app(name: &str) -> App {
+		let mut app = App::new();
+
+		app.add_plugins((MinimalPlugins, Grid))
+			.add_event::<UpdateTileEvent>()
+			.add_event::<AddLightSourceEvent>()
+			.add_event::<LightingUpdateEvent>()
+			.insert_resource(WorldGenerator::from_seed(DEFAULT_SEED))
+			.insert_resource(temp_storage(name));
+
+		app
+	}
+
+	/// Updates until `expected` chunks are loaded, checking the materialize budget every frame.
+	fn update_until_loaded(app: &mut App, expected: usize) {
+		let started = Instant::now();
+		let mut loaded = app.world().resource::<Map>().len();
+
+		while loaded < expected {
+			assert!(
+				started.elapsed() < Duration::from_secs(30),
+				"Only {loaded} of {expected} chunks were generated"
+			);
+
+			thread::sleep(Duration::from_millis(5));
+			app.update();
+
+			let now_loaded = app.world().resource::<Map>().len();
+			assert!(now_loaded.saturating_sub(loaded) <= CHUNK_MATERIALIZE_BUDGET);
+			loaded = now_loaded;
+		}
+
+		assert!(loaded == expected);
+	}
+
+	#[test]
+	fn chunks_are_materialized_within_budget() {
+		let mut app = loader_app("materialized");
+
+		app.world_mut()
+			.spawn((ChunkLoader::default(), Position(Vec2::ZERO)));
+
+		app.update();
+
+		// the loader's own chunk is loaded right away
+		assert!(app.world().resource::<Map>().contains_key(&(0, 0)));
+
+		let diameter = CHUNK_LOAD_RADIUS as usize * 2 + 1;
+		update_until_loaded(&mut app, diameter * diameter);
+	}
+
+	#[test]
+	fn chunk_loaders_keep_their_areas_loaded() {
+		let mut app = loader_app("loaders");
+		let chunk_width = CHUNK_SIZE.0 as f32 * TILE_SIZE.x as f32;
+
+		app.world_mut().spawn((
+			ChunkLoader {
+				radius: 1,
+				render_radius: 0,
+			},
+			Position(Vec2::ZERO),
+		));
+
+		let far_loader = app
+			.world_mut()
+			.spawn((
+				ChunkLoader {
+					radius: 1,
+					render_radius: 0,
+				},
+				Transform::from_xyz(chunk_width * 10.5, 0.0, 0.0),
+			))
+			.id();
+
+		// both loaders' own chunks are loaded right away
+		app.update();
+		update_until_loaded(&mut app, 18);
+
+		assert!(app.world().resource::<Map>().contains_key(&(11, -1)));
+
+		app.world_mut().despawn(far_loader);
+		app.update();
+
+		let map = app.world().resource::<Map>();
+		assert!(map.len() == 9);
+		assert!(map.keys().all(|(x, y)| x.abs() <= 1 && y.abs() <= 1));
+	}
+
+	#[test]
+	fn chunk_tiers_follow_loader_distance() {
+		let mut app = loader_app("tiers");
+		let chunk_width = CHUNK_SIZE.0 as f32 * TILE_SIZE.x as f32;
+
+		let loader = app
+			.world_mut()
+			.spawn((
+				ChunkLoader {
+					radius: 2,
+					render_radius: 0,
+				},
+				Position(Vec2::ZERO),
+			))
+			.id();
+
+		app.update();
+		update_until_loaded(&mut app, 25);
+		app.update();
+
+		let tier =
+			|app: &App, x: i32, y: i32| app.world().resource::<Map>().get(&(x, y)).map(|c| c.tier);
+
+		assert!(tier(&app, 0, 0) == Some(ChunkTier::Rendered));
+		assert!(tier(&app, 1, -1) == Some(ChunkTier::Simulated));
+		assert!(tier(&app, -2, 1) == Some(ChunkTier::Frozen));
+
+		// moving one chunk keeps the chunks behind the loader frozen within the unload margin
+		app.world_mut().get_mut::<Position>(loader).unwrap().0.x = chunk_width * 1.5;
+		app.update();
+
+		assert!(tier(&app, 0, 0) == Some(ChunkTier::Simulated));
+		assert!(tier(&app, -1, 0) == Some(ChunkTier::Frozen));
+		assert!(tier(&app, -2, 0) == Some(ChunkTier::Frozen));
+	}
+
+	#[test]
+	fn structures_survive_reloading_untouched_chunks() {
+		let generator = WorldGenerator::from_seed(DEFAULT_SEED);
+
+		// a chunk that a structure feature changes
+		let chunk_pos = (0..16)
+			.flat_map(|x| (-8..8).map(move |y| IVec2::new(x, y)))
+			.find(|pos| {
+				let generated = generator.generate_chunk(*pos);
+				let mut tiles = generated.clone();
+				StructureWrites::default().apply(&generator, *pos, &mut tiles);
+				tiles != generated
+			})
+			.unwrap();
+
+		let mut app = loader_app("structures");
+
+		let reload = move |mut commands: Commands,
+		                   mut map: ResMut<Map>,
+		                   storage: Res<ChunkStorage>,
+		                   generator: Res<WorldGenerator>,
+		                   mut structures: ResMut<StructureWrites>,
+		                   mut ev_update: EventWriter<UpdateTileEvent>,
+		                   mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
+		                   mut ev_updatelighting: EventWriter<LightingUpdateEvent>| {
+			if map.contains_key(&(chunk_pos.x, chunk_pos.y)) {
+				despawn_chunk(&mut commands, chunk_pos, &mut map, &storage);
+			}
+
+			spawn_chunk(
+				&mut commands,
+				chunk_pos,
+				&mut map,
+				&storage,
+				&generator,
+				&mut structures,
+				&mut ev_update,
+				&mut ev_addlightsource,
+				&mut ev_updatelighting,
+			);
+		};
+
+		let tiles = |app: &App| {
+			app.world().resource::<Map>()[&(chunk_pos.x, chunk_pos.y)]
+				.iter()
+				.map(|(_, t)| t.tile_type)
+				.collect::<Vec<_>>()
+		};
+
+		app.world_mut().run_system_once(reload);
+		let generated = tiles(&app);
+
+		app.world_mut().run_system_once(reload);
+		assert!(tiles(&app) == generated);
+	}
+
+	/// The chunk storage before tiles were kept in arrays, looked up the same way.
+	type HashedMap = HashMap<(i32, i32), HashMap<(u8, u8), MapTile>>;
+
+	fn hashed_get_tile(map: &HashedMap, coord: Coordinate) -> Option<MapTile> {
+		let chunk_coord = coord.as_chunk_coord();
+		let chunklocal_coord = coord.as_chunklocal_coord();
+
+		map.get(&(chunk_coord.x_i32(), chunk_coord.y_i32()))?
+			.get(&(chunklocal_coord.x_u8(), chunklocal_coord.y_u8()))
+			.copied()
+	}
+
+	/// Chunks -1..=1 in both directions, with the tile type depending on the coordinate.
+	fn test_maps() -> (Map, HashedMap) {
+		let mut map = Map(HashMap::new());
+		let mut hashed = HashMap::new();
+
+		for cx in -1..=1 {
+			for cy in -1..=1 {
+				let mut tiles = vec![];
+
+				for x in 0..CHUNK_SIZE.0 {
+					for y in 0..CHUNK_SIZE.1 {
+						let tile_x = cx * CHUNK_SIZE.0 as i32 + x as i32;
+						let tile_y = cy * CHUNK_SIZE.1 as i32 + y as i32;
+
+						tiles.push(MapTile {
+							light: [0; 3],
+							sky_light: 0,
+							lit: false,
+							outline_id: 0,
+							texture_index: None,
+							tile_type: if (tile_x * 7 + tile_y * 3) % 5 == 0 {
+								TileType::named("dirt")
+							} else {
+								TileType::EMPTY
+							},
+							tile_coord: Coordinate::Tile {
+								x: tile_x,
+								y: tile_y,
+							},
+						});
+					}
+				}
+
+				hashed.insert(
+					(cx, cy),
+					(0..CHUNK_SIZE.0)
+						.flat_map(|x| (0..CHUNK_SIZE.1).map(move |y| (x, y)))
+						.map(|(x, y)| ((x, y), tiles[chunk_index(x as i32, y as i32)]))
+						.collect(),
+				);
+				map.insert((cx, cy), MapChunk::new(Entity::PLACEHOLDER, tiles));
+			}
+		}
+
+		(map, hashed)
+	}
+
+	/// Every tile of the center chunk with its 3x3 neighborhood, the access pattern of physics.
+	fn neighborhoods() -> impl Iterator<Item = (Coordinate, Coordinate)> {
+		(0..CHUNK_SIZE.0 as i32)
+			.flat_map(|x| (0..CHUNK_SIZE.1 as i32).map(move |y| (x, y)))
+			.flat_map(|(x, y)| {
+				(-1..=1).flat_map(move |dx| {
+					(-1..=1).map(move |dy| {
+						(
+							Coordinate::Tile { x, y },
+							Coordinate::Tile {
+								x: x + dx,
+								y: y + dy,
+							},
+						)
+					})
+				})
+			})
+	}
+
+	#[test]
+	fn active_tiles_scan_in_order() {
+		let mut tiles = ActiveTiles::default();
+
+		for key in [(3, 1), (0, 2), (5, 1), (3, 0)] {
+			assert!(tiles.insert(key));
+		}
+
+		assert!(!tiles.insert((3, 1)));
+		assert!(tiles.scan(ScanOrder::Rows) == [(3, 0), (3, 1), (5, 1), (0, 2)]);
+		assert!(tiles.scan(ScanOrder::RowsDownward) == [(0, 2), (3, 1), (5, 1), (3, 0)]);
+		assert!(
+			tiles.scan(ScanOrder::Columns {
+				right_to_left: false
+			}) == [(0, 2), (3, 0), (3, 1), (5, 1)]
+		);
+		assert!(
+			tiles.scan(ScanOrder::Columns {
+				right_to_left: true
+			}) == [(5, 1), (3, 0), (3, 1), (0, 2)]
+		);
+
+		for key in [(3, 1), (0, 2), (5, 1)] {
+			assert!(tiles.remove(key));
+		}
+
+		assert!(tiles.scan(ScanOrder::Rows) == [(3, 0)]);
+		assert!(tiles.remove((3, 0)));
+		assert!(tiles.is_empty());
+		assert!(tiles.scan(ScanOrder::Rows).is_empty());
+	}
+
+	#[test]
+	fn bulk_accessors_match_get_tile() {
+		let (map, hashed) = test_maps();
+		let size = CHUNK_SIZE.0 as i32;
+
+		for (_, coord) in neighborhoods() {
+			assert!(map.get_tile(coord).map(|t| t.tile_coord) == Some(coord));
+			assert!(
+				map.get_tile(coord).map(|t| t.tile_type)
+					== hashed_get_tile(&hashed, coord).map(|t| t.tile_type)
+			);
+		}
+
+		// crosses both chunk borders of the center chunk and runs into unloaded chunks
+		let row = map
+			.line(Coordinate::Tile { x: -size - 2, y: 5 }, IVec2::X)
+			.take(size as usize * 3 + 4)
+			.collect::<Vec<_>>();
+
+		for (i, t) in row.iter().enumerate() {
+			let coord = Coordinate::Tile {
+				x: -size - 2 + i as i32,
+				y: 5,
+			};
+			assert!(t.map(|t| t.tile_coord) == map.get_tile(coord).map(|t| t.tile_coord));
+		}
+
+		assert!(row[0].is_none() && row[2].is_some() && row[row.len() - 1].is_none());
+
+		let column = map
+			.line(Coordinate::Tile { x: 3, y: size + 1 }, IVec2::NEG_Y)
+			.take(size as usize + 4)
+			.collect::<Vec<_>>();
+
+		for (i, t) in column.iter().enumerate() {
+			let coord = Coordinate::Tile {
+				x: 3,
+				y: size + 1 - i as i32,
+			};
+			assert!(t.map(|t| t.tile_coord) == map.get_tile(coord).map(|t| t.tile_coord));
+		}
+
+		let min = IVec2::new(-3, -2);
+		let max = IVec2::new(size + 1, 4);
+		let region = map.region(min, max).collect::<Vec<_>>();
+		let width = max.x - min.x + 1;
+
+		assert!(region.len() as i32 == width * (max.y - min.y + 1));
+
+		for (i, t) in region.iter().enumerate() {
+			let coord = Coordinate::Tile {
+				x: min.x + i as i32 % width,
+				y: min.y + i as i32 / width,
+			};
+			assert!(t.map(|t| t.tile_coord) == Some(coord));
+		}
+	}
+
+	/// Compares the old and new access paths, run with
+	/// `cargo test --release -- --ignored --nocapture`.
+	#[test]
+	#[ignore]
+	fn bench_tile_access() {
+		const ROUNDS: u32 = 200;
+		let (map, hashed) = test_maps();
+
+		let time = |name: &str, f: &dyn Fn() -> usize| {
+			let start = Instant::now();
+			let mut found = 0;
+
+			for _ in 0..ROUNDS {
+				found += black_box(f());
+			}
+
+			println!(
+				"{name}: {:?} per round ({found} tiles)",
+				start.elapsed() / ROUNDS
+			);
+		};
+
+		time("neighbors, hashed chunk tiles", &|| {
+			neighborhoods()
+				.filter_map(|(_, c)| hashed_get_tile(&hashed, black_box(c)))
+				.count()
+		});
+
+		time("neighbors, get_tile", &|| {
+			neighborhoods()
+				.filter_map(|(_, c)| map.get_tile(black_box(c)))
+				.count()
+		});
+
+		time("neighbors, region", &|| {
+			neighborhoods()
+				.filter(|(center, c)| center == c)
+				.flat_map(|(center, _)| {
+					let center = IVec2::new(center.x_i32(), center.y_i32());
+					map.region(center - IVec2::ONE, center + IVec2::ONE)
+				})
+				.flatten()
+				.count()
+		});
+
+		let size = CHUNK_SIZE.0 as i32;
+
+		time("rows, hashed chunk tiles", &|| {
+			(-size..size * 2)
+				.flat_map(|y| (-size..size * 2).map(move |x| Coordinate::Tile { x, y }))
+				.filter_map(|c| hashed_get_tile(&hashed, black_box(c)))
+				.count()
+		});
+
+		time("rows, line", &|| {
+			(-size..size * 2)
+				.flat_map(|y| {
+					map.line(Coordinate::Tile { x: -size, y }, IVec2::X)
+						.take(size as usize * 3)
+				})
+				.flatten()
+				.count()
+		});
+	}
+}
@@ -1,12 +1,13 @@
 use crate::{
 	players::{Jumping, MoveDirection, Player},
+	saves::SaveWorldEvent,
 	settings::Settings,
 	ScreenCursor, WorldCursor,
 };
 use bevy::{
 	prelude::{
-		App, ButtonInput, Camera, GlobalTransform, KeyCode, Plugin, Query, Res, Transform, Update,
-		With, Without,
+		App, ButtonInput, Camera, EventWriter, GlobalTransform, KeyCode, Plugin, Query, Res,
+		Transform, Update, With, Without,
 	},
 	ui::{Style, Val},
 	window::Window,
@@ -24,6 +25,7 @@ pub struct KeyBinds {
 	pub move_left: KeyBind,
 	pub move_right: KeyBind,
 	pub jump: KeyBind,
+	pub quicksave: KeyBind,
 }
 
 impl Default for KeyBinds {
@@ -41,6 +43,10 @@ impl Default for KeyBinds {
 				primary: Some(KeyCode::KeyW),
 				secondary: Some(KeyCode::Space),
 			},
+			quicksave: KeyBind {
+				primary: Some(KeyCode::F5),
+				secondary: None,
+			},
 		}
 	}
 }
@@ -88,7 +94,12 @@ fn keyboard_events_system(
 	input: Res<ButtonInput<KeyCode>>,
 	settings: Res<Settings>,
 	mut q_player: Query<(&Player, &mut MoveDirection, &mut Jumping)>,
+	mut ev_save: EventWriter<SaveWorldEvent>,
 ) {
+	if settings.keybinds.quicksave.just_pressed(&input) {
+		ev_save.send(SaveWorldEvent);
+	}
+
 	for (player, mut move_direction, mut jumping) in &mut q_player {
 		if let Player::Local = player {
 			let mut dir = MoveDirection::None;
@@ -0,0 +1,74 @@
+use crate::{light::LightBackend, worldgen::DEFAULT_SEED};
+use std::env;
+
+const DEFAULT_WORLD_NAME: &str = "world";
+
+pub struct LaunchOptions {
+	pub world_name: String,
+	pub new_world: bool,
+	pub seed: u32,
+	pub headless: bool,
+	pub ticks: Option<u64>,
+	pub light_backend: LightBackend,
+}
+
+impl Default for LaunchOptions {
+	fn default() -> Self {
+		Self {
+			world_name: DEFAULT_WORLD_NAME.to_string(),
+			new_world: false,
+			seed: DEFAULT_SEED,
+			headless: false,
+			ticks: None,
+			light_backend: LightBackend::default(),
+		}
+	}
+}
+
+impl LaunchOptions {
+	/// Parses `--world <name>` (boot into a named save, created if missing),
+	/// `--new` (require that the save doesn't exist yet),
+	/// `--seed <n>` (world generation seed, only used when a save is created),
+	/// `--headless` (simulate without a window or renderer)
+	/// `--ticks <n>` (save and exit after n ticks)
+	/// and `--light <rays|flood>` (how light is spread, see `LightBackend`).
+	pub fn from_args() -> Result<Self, String> {
+		let mut options = Self::default();
+		let mut args = env::args().skip(1);
+
+		while let Some(arg) = args.next() {
+			match arg.as_str() {
+				"--world" => {
+					options.world_name = args
+						.next()
+						.ok_or_else(|| "--world requires a save name".to_string())?;
+				}
+				"--new" => options.new_world = true,
+				"--seed" => {
+					options.seed = args
+						.next()
+						.and_then(|v| v.parse().ok())
+						.ok_or_else(|| "--seed requires an unsigned integer".to_string())?;
+				}
+				"--headless" => options.headless = true,
+				"--ticks" => {
+					options.ticks = Some(
+						args.next()
+							.and_then(|v| v.parse().ok())
+							.ok_or_else(|| "--ticks requires an unsigned integer".to_string())?,
+					);
+				}
+				"--light" => {
+					options.light_backend = match args.next().as_deref() {
+						Some("rays") => LightBackend::Rays,
+						Some("flood") => LightBackend::Flood,
+						_ => return Err("--light requires either rays or flood".to_string()),
+					};
+				}
+				_ => return Err(format!("Unknown argument: {arg}")),
+			}
+		}
+
+		Ok(options)
+	}
+}
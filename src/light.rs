@@ -1,13 +1,12 @@
 use crate::{
+	floodlight::flood_lighting_event,
 	grid::{Coordinate, Map, MapTile},
-	TILE_SIZE,
 };
 use bevy::{
 	prelude::{
-		App, Color, Commands, Event, EventReader, EventWriter, Plugin, ResMut, Resource, Startup,
-		Transform, Update, Vec2,
+		resource_equals, App, Color, ColorToPacked, Commands, Event, EventReader, EventWriter,
+		IntoSystemConfigs, Plugin, ResMut, Resource, Startup, Update, Vec2,
 	},
-	sprite::{Sprite, SpriteBundle},
 	utils::{HashMap, HashSet},
 };
 
@@ -17,21 +16,53 @@ impl Plugin for Light {
 	fn build(&self, app: &mut App) {
 		app.add_event::<AddLightSourceEvent>()
 			.add_event::<LightingUpdateEvent>()
-			.add_systems(Update, (add_lightsource_event, lighting_update_event))
+			.init_resource::<LightBackend>()
+			.add_systems(
+				Update,
+				(
+					(add_lightsource_event, lighting_update_event)
+						.chain()
+						.run_if(resource_equals(LightBackend::Rays)),
+					flood_lighting_event.run_if(resource_equals(LightBackend::Flood)),
+				),
+			)
 			.add_systems(Startup, initialize_lightsources);
 	}
 }
 
+/// How the light of emitters is spread over the map, chosen with `--light <rays|flood>`.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum LightBackend {
+	/// Rays are cast from each emitter to the edge of its radius and traced through the tiles.
+	#[default]
+	Rays,
+	/// Light floods out from each emitter tile by tile, see `floodlight`.
+	Flood,
+}
+
 fn initialize_lightsources(mut commands: Commands) {
 	commands.insert_resource(LightSources(HashMap::new()));
 }
 
 fn add_lightsource_event(
+	mut map: ResMut<Map>,
 	mut event_add: EventReader<AddLightSourceEvent>,
 	mut lightsources: ResMut<LightSources>,
 	mut event_lightingupdate: EventWriter<LightingUpdateEvent>,
 ) {
 	for ev in event_add.read() {
+		// the emitter may have been replaced again in the same frame
+		if !map
+			.get_tile(ev.0.tile_coord)
+			.is_some_and(|t| t.tile_type.get_emitter() == ev.0.tile_type.get_emitter())
+		{
+			continue;
+		}
+
+		for c in lightsources.remove_lightsource(ev.0.tile_coord, &mut map) {
+			event_lightingupdate.send(LightingUpdateEvent(c));
+		}
+
 		lightsources.add_lightsource(ev.0, &mut event_lightingupdate);
 	}
 }
@@ -40,99 +71,97 @@ fn lighting_update_event(
 	mut map: ResMut<Map>,
 	mut ev_update_l: EventReader<LightingUpdateEvent>,
 	mut lightsources: ResMut<LightSources>,
-	mut commands: Commands,
 ) {
+	let mut updates = vec![];
+
+	// light sources whose emitter was replaced or unloaded are removed before anything is
+	// traced, so the tiles they lit are relit by the remaining ones below
 	for ev in ev_update_l.read() {
 		if let Some(t) = map.get_tile(ev.0) {
 			if !t.tile_type.is_emitter() {
-				lighting_update(
-					&mut lightsources,
-					ev.0,
-					&mut map,
-					&mut commands,
-					HashMap::new(),
-				);
+				updates.extend(lightsources.remove_lightsource(ev.0, &mut map));
 			}
+
+			updates.push(ev.0);
+		}
+	}
+
+	let unloaded = lightsources
+		.0
+		.keys()
+		.map(|k| Coordinate::Tile { x: k.0, y: k.1 })
+		.filter(|c| map.get_tile(*c).is_none())
+		.collect::<Vec<_>>();
+
+	for c in unloaded {
+		updates.extend(lightsources.remove_lightsource(c, &mut map));
+	}
+
+	// rays are shared between all updates of a frame, so a ray crossing many changed
+	// tiles is only traced once
+	let mut checked_rays = HashMap::new();
+
+	for c in updates {
+		if map.get_tile(c).is_some() {
+			checked_rays = lighting_update(&mut lightsources, c, &mut map, checked_rays);
 		}
 	}
 }
 
+/// Retraces every ray passing through `coord` that wasn't traced yet this frame, and sets the
+/// tiles on them to the sum of the colored light all light sources give them.
 fn lighting_update(
 	lightsources: &mut LightSources,
 	coord: Coordinate,
 	map: &mut Map,
-	commands: &mut Commands,
 	mut checked_rays: HashMap<(i32, i32), HashSet<u16>>,
 ) -> HashMap<(i32, i32), HashSet<u16>> {
-	let mut new_light_levels: HashMap<(i32, i32), u8> = HashMap::new();
-	let mut c_vec = vec![coord];
-	let mut updated_tiles = HashSet::new();
+	// the tile itself is always set, in case it was darkened by a removed light source
+	let mut updated_tiles = HashSet::from([(coord.x_i32(), coord.y_i32())]);
 
-	while let Some(c) = c_vec.pop() {
-		if lightsources.0.is_empty() {
-			break;
-		}
+	for (pos, lightsource) in lightsources.0.iter_mut() {
+		let checked = checked_rays.entry(*pos).or_default();
+		updated_tiles.extend(lightsource.update_light_tile(map, coord, checked));
+	}
 
-		for lightsource in lightsources.0.iter_mut() {
-			let new_set = &HashSet::new();
-			let rays = checked_rays.get(lightsource.0).unwrap_or(new_set);
-			let update = lightsource.1.update_light_tile(map, c, rays);
-			checked_rays.insert(*lightsource.0, rays.union(&update.1).cloned().collect());
-
-			for (k, v) in update.0.iter() {
-				if let Some(prev) = new_light_levels.get(k) {
-					new_light_levels.insert(
-						*k,
-						if *prev > v.light_level {
-							*prev
-						} else {
-							v.light_level
-						},
-					);
-				} else {
-					new_light_levels.insert(*k, v.light_level);
-				};
+	for k in updated_tiles {
+		let coord = Coordinate::Tile { x: k.0, y: k.1 };
+		let mut light = None;
 
-				let new_c = Coordinate::Tile { x: k.0, y: k.1 };
+		for lightsource in lightsources.0.values() {
+			let light_tile = match lightsource.tiles.get(&k) {
+				Some(v) => v,
+				None => continue,
+			};
 
-				if new_c != c && !updated_tiles.contains(&(new_c.x_i32(), new_c.y_i32())) {
-					updated_tiles.insert((new_c.x_i32(), new_c.y_i32()));
-					c_vec.push(new_c);
-				}
+			let rgb = lightsource.emitter.rgb();
+			let light = light.get_or_insert([0u8; 3]);
+
+			for (l, c) in light.iter_mut().zip(rgb) {
+				let c = (light_tile.light_level as u16 * c as u16 / u8::MAX as u16) as u8;
+				*l = l.saturating_add(c);
 			}
 		}
-	}
 
-	for (k, lvl) in new_light_levels.iter() {
-		let coord = Coordinate::Tile { x: k.0, y: k.1 };
+		let light = match light {
+			Some(v) => v,
+			None => continue, // out of reach of every light source
+		};
 
-		if let Some(t) = map.get_tile(coord) {
-			if t.light_level == *lvl {
-				continue;
-			}
-		} else {
-			continue;
-		}
+		let chunk = match map.get_chunk_mut(coord) {
+			Some(v) => v,
+			None => continue,
+		};
+		let chunklocal_coord = coord.as_chunklocal_coord();
+		let t = chunk.tile_mut(chunklocal_coord.x_u8(), chunklocal_coord.y_u8());
 
-		if let Some(t) = map.get_tile_mut(coord) {
-			t.light_level = *lvl;
+		if t.light == light && t.lit {
+			continue;
 		}
 
-		if let Some(t) = map.get_tile(coord) {
-			if let Some(mut e) = commands.get_entity(t.light_entity) {
-				let color = Color::srgba_u8(0, 0, 0, u8::MAX - lvl);
-
-				e.insert(SpriteBundle {
-					sprite: Sprite {
-						color,
-						custom_size: Some(Vec2::new(TILE_SIZE.x as f32, TILE_SIZE.y as f32)),
-						..Default::default()
-					},
-					transform: Transform::from_xyz(0.0, 0.0, 3.0),
-					..Default::default()
-				});
-			}
-		}
+		t.light = light;
+		t.lit = true;
+		chunk.mark_redraw(coord);
 	}
 
 	checked_rays
@@ -147,7 +176,6 @@ impl LightSources {
 		maptile: MapTile,
 		ev_lighting_update: &mut EventWriter<LightingUpdateEvent>,
 	) {
-		println!("lightsource added");
 		let coord = maptile.tile_coord;
 
 		let emitter = if let Ok(e) = maptile.tile_type.get_emitter() {
@@ -175,127 +203,164 @@ impl LightSources {
 			}
 		}
 
-		println!("radius: {}", emitter.radius);
-		println!("rays: {}", rays.len());
-
 		let lightsource = LightSource::new(emitter, rays);
 		ev_lighting_update.send(LightingUpdateEvent(maptile.tile_coord)); //todo delay
 		self.0.insert((coord.x_i32(), coord.y_i32()), lightsource);
 	}
+
+	/// Removes the light source at `coord`, if there is one, and darkens every tile it lit.
+	/// Returns those tiles, since other light sources may still reach them.
+	fn remove_lightsource(&mut self, coord: Coordinate, map: &mut Map) -> Vec<Coordinate> {
+		let lightsource = match self.0.remove(&(coord.x_i32(), coord.y_i32())) {
+			Some(v) => v,
+			None => return vec![],
+		};
+
+		let mut darkened = vec![];
+
+		for (k, light_tile) in lightsource.tiles.iter() {
+			if light_tile.light_level == 0 {
+				continue;
+			}
+
+			let c = Coordinate::Tile { x: k.0, y: k.1 };
+
+			if let Some(chunk) = map.get_chunk_mut(c) {
+				let chunklocal_coord = c.as_chunklocal_coord();
+				let t = chunk.tile_mut(chunklocal_coord.x_u8(), chunklocal_coord.y_u8());
+
+				if t.light != [0; 3] {
+					t.light = [0; 3];
+					chunk.mark_redraw(c);
+				}
+			}
+
+			darkened.push(c);
+		}
+
+		darkened
+	}
 }
 
 struct LightSource {
 	emitter: Emitter,
 	rays: HashMap<u16, Vec<Coordinate>>,
+	/// Light level of every tile along each ray, as of the last time the ray was traced.
+	ray_levels: HashMap<u16, Vec<u8>>,
 	tiles: HashMap<(i32, i32), LightTile>,
 }
 
 impl LightSource {
 	fn new(emitter: Emitter, rays: HashMap<u16, Vec<Coordinate>>) -> Self {
 		let mut tiles: HashMap<(i32, i32), LightTile> = HashMap::new();
+		let mut ray_levels = HashMap::new();
 
-		for ray in rays.iter() {
-			for c in ray.1 {
-				let key = &(c.x_i32(), c.y_i32());
-				if let Some(existing) = tiles.get(key) {
-					let mut ray_indexes = existing.ray_indexes.clone();
-					ray_indexes.push(*ray.0);
-					tiles.insert(
-						*key,
-						LightTile {
-							ray_indexes,
-							..Default::default()
-						},
-					);
-				} else {
-					tiles.insert(
-						*key,
-						LightTile {
-							ray_indexes: vec![*ray.0],
-							..Default::default()
-						},
-					);
-				}
+		for (index, ray) in rays.iter() {
+			let center = ray.first().unwrap();
+
+			for (step, c) in ray.iter().enumerate() {
+				let tile = tiles.entry((c.x_i32(), c.y_i32())).or_insert(LightTile {
+					distance: f32::sqrt(
+						(center.x_f32() - c.x_f32()).powf(2.0)
+							+ (center.y_f32() - c.y_f32()).powf(2.0),
+					),
+					..Default::default()
+				});
+
+				tile.ray_steps.push((*index, step));
 			}
+
+			ray_levels.insert(*index, vec![0; ray.len()]);
 		}
 
 		Self {
 			emitter,
 			rays,
+			ray_levels,
 			tiles,
 		}
 	}
 
+	/// Traces the rays through `coord` that aren't in `checked` and adds them to it. Returns
+	/// every tile on them, whose light level from this source is updated.
 	fn update_light_tile(
 		&mut self,
 		map: &Map,
 		coord: Coordinate,
-		checked: &HashSet<u16>,
-	) -> (HashMap<(i32, i32), LightTile>, HashSet<u16>) {
-		let mut updated_light_tiles: HashMap<(i32, i32), LightTile> = HashMap::new();
-		let mut checked_rays = HashSet::new();
-
-		if let Some(prev_light_tile) = self.tiles.get(&(coord.x_i32(), coord.y_i32())) {
-			for index in prev_light_tile.ray_indexes.clone() {
-				if checked.contains(&index) {
-					continue;
-				}
-				checked_rays.insert(index);
-				if let Some(r) = self.rays.get(&index) {
-					#[allow(unused_mut)] //todo
-					let mut obstructed = false;
-					//let mut passed_target = false;
-					let center = r.first().unwrap();
-					for c in r {
-						if let Some(maptile) = map.get_tile(*c) {
-							//if *c == coord {
-							//	passed_target = true;
-							//}
-							let k = (c.x_i32(), c.y_i32());
-							if updated_light_tiles.get(&k).is_some() {
-								//continue;
-							};
-							let distance = f32::sqrt(
-								(center.x_f32() - c.x_f32()).powf(2.0)
-									+ (center.y_f32() - c.y_f32()).powf(2.0),
-							);
-
-							let new_light_level = if obstructed {
-								0
-							} else {
-								let r_f32 = self.emitter.radius as f32;
-								(u8::MAX as f32 * ((r_f32 - distance) / r_f32)) as u8
-							};
-
-							//if new_light_level > prev_level {
-							updated_light_tiles.insert(
-								k,
-								LightTile {
-									distance,
-									light_level: new_light_level,
-									ray_indexes: prev_light_tile.ray_indexes.clone(),
-								},
-							);
-							if maptile.tile_type.is_opaque() && !maptile.tile_type.is_emitter() {
-								//obstructed = true;
-							}
-						}
-					}
-				}
+		checked: &mut HashSet<u16>,
+	) -> Vec<(i32, i32)> {
+		let ray_steps = match self.tiles.get(&(coord.x_i32(), coord.y_i32())) {
+			Some(v) => v.ray_steps.clone(),
+			None => return vec![],
+		};
+
+		let mut updated_light_tiles = vec![];
+
+		for (index, _) in ray_steps {
+			if !checked.insert(index) {
+				continue;
 			}
+
+			let r = match self.rays.get(&index) {
+				Some(v) => v,
+				None => continue,
+			};
+
+			let levels = self.trace(map, r);
+			self.ray_levels.insert(index, levels);
+			updated_light_tiles.extend(r.iter().map(|c| (c.x_i32(), c.y_i32())));
 		}
-		for (k, v) in updated_light_tiles.iter() {
-			self.tiles.insert(*k, v.clone());
+
+		// a tile reached by several rays is as bright as the brightest one
+		for k in updated_light_tiles.iter() {
+			let light_tile = self.tiles.get_mut(k).unwrap();
+
+			light_tile.light_level = light_tile
+				.ray_steps
+				.iter()
+				.map(|(index, step)| self.ray_levels[index][*step])
+				.max()
+				.unwrap_or(0);
 		}
-		(updated_light_tiles, checked_rays)
+
+		updated_light_tiles
+	}
+
+	/// Light level of every tile along `ray`. Tiles let through as much light as their opacity
+	/// allows, except for emitters, and nothing gets past tiles in unloaded chunks.
+	fn trace(&self, map: &Map, ray: &[Coordinate]) -> Vec<u8> {
+		let r_f32 = self.emitter.radius as f32;
+		let mut tiles = map.cursor();
+		let mut transmitted = 1.0;
+
+		ray.iter()
+			.map(|c| {
+				let maptile = match tiles.get(*c) {
+					Some(v) => v,
+					None => {
+						transmitted = 0.0;
+						return 0;
+					}
+				};
+
+				let distance = self.tiles[&(c.x_i32(), c.y_i32())].distance;
+				let level = u8::MAX as f32 * ((r_f32 - distance) / r_f32).max(0.0) * transmitted;
+
+				if !maptile.tile_type.is_emitter() {
+					transmitted *= 1.0 - maptile.tile_type.get_opacity() as f32 / u8::MAX as f32;
+				}
+
+				level as u8
+			})
+			.collect()
 	}
 }
 
 #[derive(Clone)]
 struct LightTile {
-	#[allow(dead_code)] //todo
 	distance: f32,
-	ray_indexes: Vec<u16>,
+	/// Every ray passing through the tile, with how many steps along the ray it is.
+	ray_steps: Vec<(u16, usize)>,
 	light_level: u8,
 }
 
@@ -303,7 +368,7 @@ impl Default for LightTile {
 	fn default() -> Self {
 		Self {
 			distance: 0.0,
-			ray_indexes: vec![],
+			ray_steps: vec![],
 			light_level: 0,
 		}
 	}
@@ -315,6 +380,19 @@ pub struct Emitter {
 	pub color: Option<Color>,
 }
 
+impl Emitter {
+	/// sRGB channels of the light's color, white if it has none.
+	pub fn rgb(&self) -> [u8; 3] {
+		match self.color {
+			Some(v) => {
+				let [r, g, b, _] = v.to_srgba().to_u8_array();
+				[r, g, b]
+			}
+			None => [u8::MAX; 3],
+		}
+	}
+}
+
 impl Default for Emitter {
 	fn default() -> Self {
 		Self {
@@ -329,3 +407,190 @@ pub struct AddLightSourceEvent(pub MapTile);
 
 #[derive(Event)]
 pub struct LightingUpdateEvent(pub Coordinate);
+
+#[cfg(test)]
+mod tests {
+	use super::LightBackend;
+	use crate::{testing::TestWorld, tiletypes::TileType};
+	use std::{hint::black_box, time::Instant};
+
+	const BACKENDS: [LightBackend; 2] = [LightBackend::Rays, LightBackend::Flood];
+
+	#[test]
+	fn removed_lanterns_stop_lighting() {
+		for backend in BACKENDS {
+			let mut world = TestWorld::with_light_backend(
+				"
+				L.............................L
+				",
+				backend,
+			);
+
+			world.tick(1);
+
+			assert!(world.light_level(1, 0) > 0);
+			assert!(world.light_level(10, 0) > 0);
+			let right = world.light_level(20, 0);
+			assert!(right > 0);
+
+			world.set_tile(0, 0, TileType::EMPTY);
+			world.tick(1);
+
+			assert_eq!(world.light_level(1, 0), 0);
+			assert_eq!(world.light_level(10, 0), 0);
+			assert_eq!(world.light_level(20, 0), right);
+
+			// lanterns in chunks that are unloaded stop lighting the chunks next to them
+			world.fill((31, 0), (39, 0), TileType::EMPTY);
+			world.set_tile(40, 0, TileType::named("lantern"));
+			world.tick(1);
+			assert!(world.light_level(31, 0) > 0);
+
+			world.set_tile(30, 0, TileType::EMPTY);
+			world.unload_chunk(1, 0);
+			world.tick(1);
+			assert_eq!(world.light_level(25, 0), 0);
+		}
+	}
+
+	#[test]
+	fn walls_block_light() {
+		for backend in BACKENDS {
+			let mut world = TestWorld::with_light_backend(
+				"
+				#############
+				L.....#......
+				#############
+				LWWWWWWWWWWWW
+				",
+				backend,
+			);
+
+			world.tick(1);
+
+			assert!(world.light_level(5, 2) > 0);
+			assert_eq!(world.light_level(8, 2), 0);
+
+			world.set_tile(6, 2, TileType::EMPTY);
+			world.tick(1);
+			let open = world.light_level(8, 2);
+			assert!(open > 0);
+
+			// light goes through water, but less of it
+			let dimmed = world.light_level(8, 0);
+			assert!(dimmed > 0 && dimmed < open);
+
+			world.set_tile(6, 2, TileType::named("dirt"));
+			world.tick(1);
+			assert_eq!(world.light_level(8, 2), 0);
+		}
+	}
+
+	#[test]
+	fn lights_are_colored() {
+		for backend in BACKENDS {
+			let mut world = TestWorld::with_light_backend(
+				"
+				F..............................L
+				",
+				backend,
+			);
+
+			world.tick(1);
+
+			// fire is red-orange and lanterns warm yellow
+			let [r, g, b] = world.light(1, 0);
+			assert!(r > g && g > b);
+			let [r, g, b] = world.light(29, 0);
+			assert!(r > g && g > b && g as u32 * 255 > r as u32 * 200);
+
+			// overlapping lights add up with rays. flooding keeps the brightest of each channel,
+			// and both lanterns are as far away, see `floodlight`
+			world.set_tile(27, 0, TileType::named("lantern"));
+			world.tick(1);
+			let [r2, g2, b2] = world.light(29, 0);
+
+			match backend {
+				LightBackend::Rays => assert!(r2 > r && g2 > g && b2 > b),
+				LightBackend::Flood => assert!([r2, g2, b2] == [r, g, b]),
+			}
+		}
+	}
+
+	#[test]
+	fn magma_pools_glow() {
+		for backend in BACKENDS {
+			let mut world = TestWorld::with_light_backend(
+				"
+				..............
+				..............
+				..#AAA#.......
+				..#####.......
+				",
+				backend,
+			);
+
+			world.tick(1);
+
+			// the pool lights the air right above it red-orange, but not the far end of the cave
+			let [r, g, b] = world.light(4, 2);
+			assert!(r > g && g > b);
+			assert_eq!(world.light_level(13, 3), 0);
+
+			// and stops once it's filled in
+			world.fill((3, 1), (5, 1), TileType::named("dirt"));
+			world.tick(1);
+			assert_eq!(world.light_level(4, 2), 0);
+		}
+	}
+
+	/// Compares the backends lighting the same map, run with
+	/// `cargo test --release -- --ignored --nocapture`. Both timings include building the test
+	/// world and ticking the other plugins.
+	#[test]
+	#[ignore]
+	fn bench_light_backends() {
+		const ROUNDS: u32 = 20;
+
+		// a cave with lanterns every 8 tiles, and a pillar in the middle that is dug out and put
+		// back every round
+		let layout = (0..32)
+			.map(|y| {
+				(0..32)
+					.map(|x| match (x, y) {
+						(0 | 31, _) | (_, 0 | 31) => '#',
+						(16, 8..=24) => '#',
+						(x, y) if x % 8 == 4 && y % 8 == 4 => 'L',
+						_ => '.',
+					})
+					.collect::<String>()
+			})
+			.collect::<Vec<_>>()
+			.join("\n");
+
+		for backend in BACKENDS {
+			let start = Instant::now();
+			let mut world = TestWorld::with_light_backend(&layout, backend);
+			let lit = start.elapsed();
+
+			let start = Instant::now();
+
+			for i in 0..ROUNDS {
+				let tile_type = if i % 2 == 0 {
+					TileType::EMPTY
+				} else {
+					TileType::named("dirt")
+				};
+
+				world.set_tile(16, 16, tile_type);
+				world.tick(1);
+				black_box(world.light_level(17, 16));
+			}
+
+			println!(
+				"{backend:?}: {lit:?} to build and light the map, {:?} per changed tile",
+				start.elapsed() / ROUNDS
+			);
+		}
+	}
+}
@@ -0,0 +1,170 @@
+use crate::{
+	grid::{Coordinate, CreateTileEvent, DestroyTileEvent, Map},
+	tilephysics::LiquidConsumedEvent,
+	tiletypes::{TileId, TileType},
+	TickTimer, CHUNK_SIZE,
+};
+use bevy::{
+	prelude::{App, EventReader, Last, Plugin, Res, ResMut, Resource},
+	utils::{HashMap, HashSet},
+};
+use std::fmt::{self, Display, Formatter};
+
+/// Debug check that the liquid solver conserves the total level of every liquid type. Each frame
+/// the liquid tiles of all loaded chunks are compared with the previous frame, and any change in a
+/// liquid's total that isn't explained by a `LiquidConsumedEvent` or a player edit is reported.
+pub struct LiquidCheck;
+
+impl Plugin for LiquidCheck {
+	fn build(&self, app: &mut App) {
+		app.init_resource::<LiquidLedger>()
+			.add_systems(Last, check_liquid_conservation);
+	}
+}
+
+#[derive(Resource, Default)]
+pub struct LiquidLedger {
+	snapshot: HashMap<(i32, i32), HashMap<(i32, i32), TileType>>,
+	edited: HashSet<(i32, i32)>,
+	pub drifts: Vec<LiquidDrift>,
+}
+
+pub struct LiquidDrift {
+	pub tick: u64,
+	pub liquid: TileType,
+	pub amount: i64,
+	/// Every tile of this liquid whose level changed during the frame the drift happened in.
+	pub coords: Vec<(i32, i32)>,
+}
+
+impl Display for LiquidDrift {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(
+			f,
+			"Liquid drift: {} changed by {} on tick {} at {:?}",
+			self.liquid, self.amount, self.tick, self.coords
+		)
+	}
+}
+
+fn check_liquid_conservation(
+	map: Res<Map>,
+	mut ledger: ResMut<LiquidLedger>,
+	ticktimer: Res<TickTimer>,
+	mut ev_consumed: EventReader<LiquidConsumedEvent>,
+	mut ev_create: EventReader<CreateTileEvent>,
+	mut ev_destroy: EventReader<DestroyTileEvent>,
+) {
+	// player and devtools edits can be applied in the frame after they were sent,
+	// so the edited tiles are excluded from this frame and the next
+	let previously_edited = std::mem::take(&mut ledger.edited);
+
+	for coord in ev_create
+		.read()
+		.filter(|ev| ev.prev_maptile.is_none())
+		.map(|ev| ev.coord)
+		.chain(ev_destroy.read().map(|ev| ev.0))
+	{
+		ledger.edited.insert(tile_key(coord));
+	}
+
+	let mut snapshot = HashMap::new();
+
+	for (chunk_pos, chunk) in map.iter() {
+		let mut liquids = HashMap::new();
+
+		for (chunklocal, maptile) in chunk.iter() {
+			if maptile.tile_type.is_liquid() {
+				liquids.insert(
+					(
+						chunk_pos.0 * CHUNK_SIZE.0 as i32 + chunklocal.0 as i32,
+						chunk_pos.1 * CHUNK_SIZE.1 as i32 + chunklocal.1 as i32,
+					),
+					maptile.tile_type,
+				);
+			}
+		}
+
+		snapshot.insert(*chunk_pos, liquids);
+	}
+
+	let mut consumed: HashMap<TileId, i64> = HashMap::new();
+
+	for ev in ev_consumed.read() {
+		let chunk_coord = ev.coord.as_chunk_coord();
+		let chunk_pos = (chunk_coord.x_i32(), chunk_coord.y_i32());
+		let coord = tile_key(ev.coord);
+
+		// only count consumption in tiles that are compared below
+		if ledger.snapshot.contains_key(&chunk_pos)
+			&& snapshot.contains_key(&chunk_pos)
+			&& !ledger.edited.contains(&coord)
+			&& !previously_edited.contains(&coord)
+		{
+			*consumed.entry(ev.tile_type.id()).or_default() += ev.tile_type.liquid().level as i64;
+		}
+	}
+
+	let mut drifts: HashMap<TileId, LiquidDrift> = HashMap::new();
+
+	// chunks that were loaded or unloaded since the last frame aren't compared
+	for (chunk_pos, liquids) in snapshot.iter() {
+		let previous = match ledger.snapshot.get(chunk_pos) {
+			Some(v) => v,
+			None => continue,
+		};
+
+		let coords = liquids
+			.keys()
+			.chain(previous.keys())
+			.collect::<HashSet<_>>();
+
+		for coord in coords {
+			if ledger.edited.contains(coord) || previously_edited.contains(coord) {
+				continue;
+			}
+
+			let before = previous.get(coord);
+			let after = liquids.get(coord);
+
+			if before == after {
+				continue;
+			}
+
+			for (tile_type, sign) in [(before, -1), (after, 1)] {
+				if let Some(t) = tile_type {
+					let drift = drifts.entry(t.id()).or_insert(LiquidDrift {
+						tick: ticktimer.1,
+						liquid: *t,
+						amount: 0,
+						coords: vec![],
+					});
+
+					drift.amount += sign * t.liquid().level as i64;
+					drift.coords.push(*coord);
+				}
+			}
+		}
+	}
+
+	for (kind, mut drift) in drifts {
+		drift.amount += consumed.get(&kind).copied().unwrap_or(0);
+
+		if drift.amount == 0 {
+			continue;
+		}
+
+		drift.coords.sort();
+		drift.coords.dedup();
+
+		println!("{drift}");
+		ledger.drifts.push(drift);
+	}
+
+	ledger.snapshot = snapshot;
+}
+
+fn tile_key(coord: Coordinate) -> (i32, i32) {
+	let coord = coord.as_tile_coord();
+	(coord.x_i32(), coord.y_i32())
+}
@@ -1,7 +1,7 @@
 #![allow(clippy::too_many_arguments)]
 
 use bevy::{
-	app::{App, Startup, Update},
+	app::{App, ScheduleRunnerPlugin, Startup, Update},
 	math::UVec2,
 	prelude::*,
 	time::Timer,
@@ -9,24 +9,43 @@ use bevy::{
 	DefaultPlugins,
 	{math::Vec3, window::Cursor},
 };
+use chunkrender::ChunkRender;
 use devtools::DevTools;
 use grid::Grid;
 use inputs::Inputs;
+use launchoptions::LaunchOptions;
 use light::Light;
+use liquidcheck::LiquidCheck;
 use playerphysics::{PlayerPhysics, Position, Velocity};
 use players::{Player, PlayerBundle, Players};
+use saves::{Saves, WorldHeader, WorldSave};
 use settings::Settings;
+use sky::Sky;
 use sprites::{setup_sprites, Sprites};
+use std::time::Duration;
 use tilephysics::TilePhysics;
+use tiletypes::TileRegistry;
+use worldgen::WorldGenerator;
 
+mod biomes;
+mod chunkrender;
 mod devtools;
+mod floodlight;
 mod grid;
 mod inputs;
+mod launchoptions;
 mod light;
+mod liquidcheck;
+mod persistence;
 mod playerphysics;
 mod players;
+mod saves;
 mod settings;
+mod sky;
 mod sprites;
+mod structures;
+#[cfg(test)]
+mod testing;
 mod tileoutline;
 mod tilephysics;
 mod tiles;
@@ -38,8 +57,13 @@ const WINDOW_DEFAULT_HEIGHT: f32 = 720.0;
 
 const CHUNK_SIZE: (u8, u8) = (32, 32);
 const TILE_SIZE: UVec2 = UVec2::new(8, 8);
-const RENDER_DISTANCE: UVec2 = UVec2::new(3, 2);
-const UNRENDER_DISTANCE: UVec2 = UVec2::new(4, 3);
+/// Radii of the player's `ChunkLoader`, in chunks.
+const CHUNK_LOAD_RADIUS: u32 = 4;
+const CHUNK_RENDER_RADIUS: u32 = 2;
+/// How many chunks past every loader's radius a chunk has to be before it is unloaded.
+const CHUNK_UNLOAD_MARGIN: u32 = 1;
+/// How many generated chunks are added to the map per frame at most.
+const CHUNK_MATERIALIZE_BUDGET: usize = 2;
 const CAMERA_PROJECTION_SCALE: f32 = 0.4;
 
 const PLAYER_SIZE: UVec2 = UVec2::new(20, 36);
@@ -69,9 +93,59 @@ struct MainCamera;
 #[derive(Resource)]
 struct TickTimer(Timer, u64);
 
+/// Tick at which a `--ticks` run saves and exits.
+#[derive(Resource)]
+struct TickLimit(u64);
+
 fn main() {
-	App::new()
-		.add_plugins((
+	let options = match LaunchOptions::from_args() {
+		Ok(v) => v,
+		Err(e) => {
+			println!("{e}");
+			return;
+		}
+	};
+
+	if let Err(e) = TileRegistry::init() {
+		println!("{e}");
+		return;
+	}
+
+	let new_world_header = WorldHeader {
+		seed: options.seed,
+		..Default::default()
+	};
+
+	let world_save = if options.new_world {
+		WorldSave::create(&options.world_name, new_world_header)
+	} else {
+		WorldSave::open_or_create(&options.world_name, new_world_header)
+	};
+
+	let world_save = match world_save {
+		Ok(v) => v,
+		Err(e) => {
+			println!("{e}");
+			return;
+		}
+	};
+
+	let mut app = App::new();
+
+	if options.headless {
+		app.add_plugins((
+			MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f32(
+				1.0 / TICKRATE,
+			))),
+			Grid,
+			TilePhysics,
+			Light,
+			Sky,
+			Saves,
+		))
+		.add_systems(Startup, startup_headless);
+	} else {
+		app.add_plugins((
 			DefaultPlugins
 				.set(WindowPlugin {
 					primary_window: Some(Window {
@@ -93,27 +167,57 @@ fn main() {
 				.set(ImagePlugin::default_nearest()),
 			Inputs,
 			Grid,
+			ChunkRender,
 			PlayerPhysics,
 			TilePhysics,
 			Players,
 			Light,
+			Sky,
+			Saves,
 			DevTools,
 		))
-		.add_event::<TickEvent>()
 		.add_systems(Startup, (setup_sprites, apply_deferred, startup).chain())
+		.insert_resource(ClearColor::default());
+	}
+
+	if cfg!(debug_assertions) {
+		app.add_plugins(LiquidCheck);
+	}
+
+	if let Some(ticks) = options.ticks {
+		app.insert_resource(TickLimit(world_save.header.tick + ticks))
+			.add_systems(Update, exit_after_tick_limit.after(tick));
+	}
+
+	app.add_event::<TickEvent>()
 		.add_systems(Update, tick)
 		.insert_resource(Settings {
 			..Default::default()
 		})
-		.insert_resource(ClearColor(Color::srgb(0.30, 0.20, 0.10)))
+		.insert_resource(options.light_backend)
 		.insert_resource(TickTimer(
 			Timer::from_seconds(1.0 / TICKRATE, TimerMode::Repeating),
-			0,
+			world_save.header.tick,
 		))
+		.insert_resource(
+			WorldGenerator::from_seed(world_save.header.seed)
+				.with_open_sky(world_save.header.open_sky),
+		)
+		.insert_resource(world_save.chunk_storage())
+		.insert_resource(world_save)
 		.run();
 }
 
-fn startup(mut commands: Commands, sprites: Res<Sprites>) {
+/// Spawns the local player without a sprite, so that chunks still stream in around it.
+fn startup_headless(mut commands: Commands, world_save: Res<WorldSave>) {
+	commands.spawn(PlayerBundle {
+		position: Position(world_save.header.player_position()),
+		velocity: Velocity(world_save.header.player_velocity()),
+		..Default::default()
+	});
+}
+
+fn startup(mut commands: Commands, sprites: Res<Sprites>, world_save: Res<WorldSave>) {
 	commands.spawn((
 		Camera2dBundle {
 			projection: OrthographicProjection {
@@ -130,14 +234,17 @@ fn startup(mut commands: Commands, sprites: Res<Sprites>) {
 
 	commands.spawn((WorldCursor, Transform::from_translation(Vec3::ZERO)));
 
+	let player_position = world_save.header.player_position();
+
 	commands.spawn((
 		SpriteBundle {
-			transform: Transform::from_translation(Vec3::new(50.0, -400.0, 10.0)),
+			transform: Transform::from_translation(player_position.extend(10.0)),
 			texture: sprites.player.clone(),
 			..Default::default()
 		},
 		PlayerBundle {
-			position: Position(Vec2::new(50.0, -400.0)),
+			position: Position(player_position),
+			velocity: Velocity(world_save.header.player_velocity()),
 			..Default::default()
 		},
 	));
@@ -196,5 +303,15 @@ fn tick(time: Res<Time>, mut timer: ResMut<TickTimer>, mut ev: EventWriter<TickE
 	}
 }
 
+fn exit_after_tick_limit(
+	timer: Res<TickTimer>,
+	limit: Res<TickLimit>,
+	mut ev_exit: EventWriter<AppExit>,
+) {
+	if timer.1 >= limit.0 {
+		ev_exit.send(AppExit::Success);
+	}
+}
+
 #[derive(Event)]
 pub struct TickEvent(u64);
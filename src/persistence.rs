@@ -0,0 +1,202 @@
+use crate::{
+	grid::MapChunk,
+	light::Emitter,
+	tiletypes::{registry, Liquid, TileId, TileType},
+	CHUNK_SIZE,
+};
+use bevy::prelude::{Color, ColorToPacked, IVec2, Resource};
+use std::{fs, path::PathBuf};
+
+const CHUNK_FILE_MAGIC: &[u8; 4] = b"TGCH";
+const CHUNK_FORMAT_VERSION: u8 = 1;
+
+#[derive(Resource, Clone)]
+pub struct ChunkStorage {
+	directory: PathBuf,
+}
+
+impl ChunkStorage {
+	pub fn new(directory: impl Into<PathBuf>) -> Self {
+		Self {
+			directory: directory.into(),
+		}
+	}
+
+	fn chunk_path(&self, chunk_pos: IVec2) -> PathBuf {
+		self.directory
+			.join(format!("{}_{}.chunk", chunk_pos.x, chunk_pos.y))
+	}
+
+	pub fn save_chunk(&self, chunk_pos: IVec2, chunk: &MapChunk) {
+		let mut tile_types = vec![];
+
+		for (_, t) in chunk.iter() {
+			tile_types.push(t.tile_type);
+		}
+
+		if let Err(e) = fs::create_dir_all(&self.directory) {
+			println!("Chunk save error: {e}");
+			return;
+		}
+
+		if let Err(e) = fs::write(self.chunk_path(chunk_pos), encode_chunk(&tile_types)) {
+			println!("Chunk save error: {e}");
+		}
+	}
+
+	/// Returns the saved tiles of a chunk in the same x-major order `spawn_chunk` iterates them,
+	/// or `None` if the chunk has never been saved.
+	pub fn load_chunk(&self, chunk_pos: IVec2) -> Option<Vec<TileType>> {
+		let bytes = fs::read(self.chunk_path(chunk_pos)).ok()?;
+
+		match decode_chunk(&bytes) {
+			Ok(v) => Some(v),
+			Err(()) => {
+				println!(
+					"Corrupt chunk file: {}",
+					self.chunk_path(chunk_pos).display()
+				);
+				None
+			}
+		}
+	}
+}
+
+fn encode_chunk(tile_types: &[TileType]) -> Vec<u8> {
+	let mut bytes = CHUNK_FILE_MAGIC.to_vec();
+	bytes.push(CHUNK_FORMAT_VERSION);
+	bytes.push(CHUNK_SIZE.0);
+	bytes.push(CHUNK_SIZE.1);
+
+	for tile_type in tile_types {
+		encode_tiletype(*tile_type, &mut bytes);
+	}
+
+	bytes
+}
+
+fn decode_chunk(bytes: &[u8]) -> Result<Vec<TileType>, ()> {
+	let mut reader = ByteReader(bytes);
+
+	if reader.take(4)? != CHUNK_FILE_MAGIC {
+		return Err(());
+	}
+
+	let version = reader.u8()?;
+
+	if version == 0
+		|| version > CHUNK_FORMAT_VERSION
+		|| reader.u8()? != CHUNK_SIZE.0
+		|| reader.u8()? != CHUNK_SIZE.1
+	{
+		return Err(());
+	}
+
+	let mut tile_types = vec![];
+
+	for _ in 0..(CHUNK_SIZE.0 as usize * CHUNK_SIZE.1 as usize) {
+		tile_types.push(decode_tiletype(&mut reader, version)?);
+	}
+
+	Ok(tile_types)
+}
+
+fn encode_tiletype(tile_type: TileType, bytes: &mut Vec<u8>) {
+	let tag = tile_type.id().0;
+
+	bytes.push(tag);
+
+	if let Ok(liquid) = tile_type.get_liquid() {
+		bytes.push(liquid.level);
+		bytes.push(match liquid.flowing_right {
+			None => 0,
+			Some(false) => 1,
+			Some(true) => 2,
+		});
+		bytes.push(liquid.momentum);
+		bytes.push(liquid.sprite_override as u8);
+	} else if let Ok(emitter) = tile_type.get_emitter() {
+		bytes.push(emitter.radius);
+
+		if let Some(color) = emitter.color {
+			bytes.push(1);
+			bytes.extend_from_slice(&color.to_srgba().to_u8_array());
+		} else {
+			bytes.push(0);
+		}
+	}
+}
+
+fn decode_tiletype(reader: &mut ByteReader, version: u8) -> Result<TileType, ()> {
+	let tile_type = match registry().tile(TileId(migrate_tile_tag(reader.u8()?, version))) {
+		Some(v) => v,
+		None => return Err(()),
+	};
+
+	Ok(if tile_type.is_liquid() {
+		tile_type.with_liquid(decode_liquid(reader)?)
+	} else if tile_type.is_emitter() {
+		tile_type.with_emitter(decode_emitter(reader)?)
+	} else {
+		tile_type
+	})
+}
+
+/// Maps a tag written by chunk format `version` to its current value. Tags are the ids of the
+/// tile definitions, so new tiles should be given a new id rather than renumbering existing ones;
+/// if a renumbering is ever unavoidable, bump `CHUNK_FORMAT_VERSION` and append a step here.
+fn migrate_tile_tag(tag: u8, version: u8) -> u8 {
+	TILE_TAG_MIGRATIONS[(version - 1) as usize..]
+		.iter()
+		.fold(tag, |tag, migration| migration(tag))
+}
+
+/// `TILE_TAG_MIGRATIONS[n]` upgrades a tag from format version `n + 1` to `n + 2`.
+const TILE_TAG_MIGRATIONS: [fn(u8) -> u8; CHUNK_FORMAT_VERSION as usize - 1] = [];
+
+fn decode_liquid(reader: &mut ByteReader) -> Result<Liquid, ()> {
+	Ok(Liquid {
+		level: reader.u8()?,
+		flowing_right: match reader.u8()? {
+			0 => None,
+			1 => Some(false),
+			2 => Some(true),
+			_ => return Err(()),
+		},
+		momentum: reader.u8()?,
+		sprite_override: reader.u8()? != 0,
+	})
+}
+
+fn decode_emitter(reader: &mut ByteReader) -> Result<Emitter, ()> {
+	let radius = reader.u8()?;
+
+	let color = match reader.u8()? {
+		0 => None,
+		1 => {
+			let c = reader.take(4)?;
+			Some(Color::srgba_u8(c[0], c[1], c[2], c[3]))
+		}
+		_ => return Err(()),
+	};
+
+	Ok(Emitter { radius, color })
+}
+
+struct ByteReader<'a>(&'a [u8]);
+
+impl<'a> ByteReader<'a> {
+	fn take(&mut self, n: usize) -> Result<&'a [u8], ()> {
+		if self.0.len() < n {
+			return Err(());
+		}
+
+		let (taken, rest) = self.0.split_at(n);
+		self.0 = rest;
+		Ok(taken)
+	}
+
+	fn u8(&mut self) -> Result<u8, ()> {
+		Ok(self.take(1)?[0])
+	}
+}
@@ -1,13 +1,10 @@
 use crate::{
-	grid::{region_collides, Chunk, Region},
+	grid::{region_collides, region_loaded, Map, Region},
 	players::OnGround,
 	Player, GRAVITY_SCALE, PLAYER_SIZE, PLAYER_UNSTUCK_NUDGE_SPEED, TERMINAL_VELOCITY, TILE_SIZE,
 };
 use bevy::{
-	prelude::{
-		App, Children, Component, Deref, DerefMut, Plugin, Query, Res, Transform, Update, Vec2,
-		With,
-	},
+	prelude::{App, Component, Deref, DerefMut, Plugin, Query, Res, Transform, Update, Vec2, With},
 	time::Time,
 };
 
@@ -19,9 +16,6 @@ impl Plugin for PlayerPhysics {
 	}
 }
 
-#[derive(Component)]
-pub struct Collider;
-
 #[derive(Component, Deref, DerefMut)]
 pub struct Gravity(pub f32);
 
@@ -69,8 +63,7 @@ fn motion_tween(mut q_objects: Query<(&mut Transform, &Position)>) {
 
 fn apply_velocity(
 	time: Res<Time>,
-	q_colliders: Query<&Region, With<Collider>>,
-	q_chunks: Query<(&Region, &Children), With<Chunk>>,
+	map: Res<Map>,
 	mut player_query: Query<(&mut Position, &mut Velocity, &mut OnGround), With<Player>>,
 ) {
 	for (mut player_position, mut player_velocity, mut on_ground) in &mut player_query {
@@ -85,7 +78,12 @@ fn apply_velocity(
 			&PLAYER_SIZE.as_vec2(),
 		);
 
-		if region_collides(&current_player_region, &q_colliders, &q_chunks) {
+		// chunks are generated in the background, wait for them instead of falling through
+		if !region_loaded(&current_player_region.expanded(&TILE_SIZE.as_vec2()), &map) {
+			continue;
+		}
+
+		if region_collides(&current_player_region, &map) {
 			on_ground.0 = false;
 			player_velocity.0 = Vec2::ZERO;
 			'outer: for m in [0.5, 1.0, 1.5] {
@@ -105,8 +103,7 @@ fn apply_velocity(
 							t.0 as f32 * TILE_SIZE.x as f32 * m,
 							t.1 as f32 * TILE_SIZE.y as f32 * m,
 						)),
-						&q_colliders,
-						&q_chunks,
+						&map,
 					) {
 						let dir = Vec2::new(
 							t.0 as f32 * PLAYER_UNSTUCK_NUDGE_SPEED,
@@ -138,26 +135,22 @@ fn apply_velocity(
 			&PLAYER_SIZE.as_vec2(),
 		);
 
-		if !region_collides(&new_player_region, &q_colliders, &q_chunks) {
+		if !region_collides(&new_player_region, &map) {
 			player_position.0 = new_pos;
-		} else if !region_collides(
-			&current_player_region.moved(&Vec2::new(delta_x, 0.0)),
-			&q_colliders,
-			&q_chunks,
-		) {
+		} else if !region_collides(&current_player_region.moved(&Vec2::new(delta_x, 0.0)), &map) {
 			//head bonk. maintain x vel
 			player_velocity.y = 0.0;
 			player_position.0.x = new_pos.x;
 		} else if player_velocity.x != 0.0 {
 			let step_up_region = new_player_region.moved(&Vec2::new(0.0, TILE_SIZE.y as f32));
 
-			if on_ground.0 && !region_collides(&step_up_region, &q_colliders, &q_chunks) {
+			if on_ground.0 && !region_collides(&step_up_region, &map) {
 				player_position.0 = Vec2::new(new_pos.x, new_pos.y + TILE_SIZE.y as f32);
 			} else {
 				player_velocity.x = 0.0;
 				let new_player_region = current_player_region.moved(&Vec2::new(0.0, delta_y));
 
-				if !region_collides(&new_player_region, &q_colliders, &q_chunks) {
+				if !region_collides(&new_player_region, &map) {
 					//stepping up
 					player_position.0.y += delta_y;
 				} else {
@@ -173,8 +166,7 @@ fn apply_velocity(
 fn apply_gravity(
 	mut query: Query<(&Gravity, &mut Velocity, &mut Position, &mut OnGround), With<Player>>,
 	time: Res<Time>,
-	q_colliders: Query<&Region, With<Collider>>,
-	q_chunks: Query<(&Region, &Children), With<Chunk>>,
+	map: Res<Map>,
 ) {
 	for (gravity, mut velocity, mut position, mut on_ground) in &mut query {
 		let player_size_halved_x = PLAYER_SIZE.x as f32 * 0.5;
@@ -188,7 +180,11 @@ fn apply_gravity(
 			&PLAYER_SIZE.as_vec2(),
 		);
 
-		if region_collides(&current_player_region, &q_colliders, &q_chunks) {
+		if !region_loaded(&current_player_region.expanded(&TILE_SIZE.as_vec2()), &map) {
+			continue;
+		}
+
+		if region_collides(&current_player_region, &map) {
 			on_ground.0 = false;
 			continue;
 		}
@@ -202,7 +198,7 @@ fn apply_gravity(
 		)
 		.moved(&Vec2::new(0.0, -1.0));
 
-		let new_on_ground = region_collides(&floor_check, &q_colliders, &q_chunks);
+		let new_on_ground = region_collides(&floor_check, &map);
 
 		if velocity.y <= 0.0 && new_on_ground {
 			//standing
@@ -219,8 +215,7 @@ fn apply_gravity(
 			//just began falling
 			if region_collides(
 				&floor_check.moved(&Vec2::new(0.0, -(TILE_SIZE.y as f32))),
-				&q_colliders,
-				&q_chunks,
+				&map,
 			) {
 				//stepping down
 				on_ground.0 = false;
@@ -1,4 +1,5 @@
 use crate::{
+	grid::ChunkLoader,
 	playerphysics::{Gravity, Position},
 	MainCamera, Velocity, WorldCursor, PLAYER_ACCEL, PLAYER_AIR_CONTROL, PLAYER_AIR_FRICTION,
 	PLAYER_JUMP_FORCE, PLAYER_SPEED,
@@ -62,6 +63,7 @@ pub struct OnGround(pub bool);
 #[derive(Bundle, Default)]
 pub struct PlayerBundle {
 	pub player: Player,
+	pub chunk_loader: ChunkLoader,
 	pub velocity: Velocity,
 	pub gravity: Gravity,
 	pub on_ground: OnGround,
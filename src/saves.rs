@@ -0,0 +1,196 @@
+use crate::{
+	grid::Map,
+	persistence::ChunkStorage,
+	playerphysics::{Position, Velocity},
+	players::Player,
+	worldgen::DEFAULT_SEED,
+	TickTimer,
+};
+use bevy::{
+	app::AppExit,
+	prelude::{App, Event, EventReader, IVec2, Last, Plugin, Query, Res, ResMut, Resource, Vec2},
+};
+use serde::{Deserialize, Serialize};
+use std::{fs, path::PathBuf};
+
+pub const SAVE_FORMAT_VERSION: u32 = 2;
+const SAVES_DIRECTORY: &str = "saves";
+const HEADER_FILE_NAME: &str = "world.ron";
+const CHUNK_DIRECTORY_NAME: &str = "chunks";
+
+pub struct Saves;
+
+impl Plugin for Saves {
+	fn build(&self, app: &mut App) {
+		app.add_event::<SaveWorldEvent>()
+			.add_systems(Last, save_world);
+	}
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct WorldHeader {
+	pub format_version: u32,
+	pub seed: u32,
+	pub tick: u64,
+	pub player_position: (f32, f32),
+	pub player_velocity: (f32, f32),
+	/// Whether the terrain stops at a surface with open sky above it. Worlds from before save
+	/// format 2 were generated without one, and keep being generated that way.
+	#[serde(default)]
+	pub open_sky: bool,
+}
+
+impl Default for WorldHeader {
+	fn default() -> Self {
+		Self {
+			format_version: SAVE_FORMAT_VERSION,
+			seed: DEFAULT_SEED,
+			tick: 0,
+			player_position: (50.0, -400.0),
+			player_velocity: (0.0, 0.0),
+			open_sky: true,
+		}
+	}
+}
+
+impl WorldHeader {
+	pub fn player_position(&self) -> Vec2 {
+		Vec2::new(self.player_position.0, self.player_position.1)
+	}
+
+	pub fn player_velocity(&self) -> Vec2 {
+		Vec2::new(self.player_velocity.0, self.player_velocity.1)
+	}
+}
+
+#[derive(Resource)]
+pub struct WorldSave {
+	pub name: String,
+	pub directory: PathBuf,
+	pub header: WorldHeader,
+}
+
+impl WorldSave {
+	/// Opens `saves/<name>`, creating a fresh world there from `header` if it doesn't exist yet.
+	pub fn open_or_create(name: &str, header: WorldHeader) -> Result<Self, String> {
+		if Self::exists(name) {
+			Self::open(name)
+		} else {
+			Self::create(name, header)
+		}
+	}
+
+	pub fn exists(name: &str) -> bool {
+		Self::directory_for(name).join(HEADER_FILE_NAME).is_file()
+	}
+
+	pub fn open(name: &str) -> Result<Self, String> {
+		let directory = Self::directory_for(name);
+		let path = directory.join(HEADER_FILE_NAME);
+
+		let contents = fs::read_to_string(&path)
+			.map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+		let header: WorldHeader = ron::from_str(&contents)
+			.map_err(|e| format!("Failed to parse {}: {e}", path.display()))?;
+
+		if header.format_version > SAVE_FORMAT_VERSION {
+			return Err(format!(
+				"World \"{name}\" uses save format {}, but this build only supports up to {SAVE_FORMAT_VERSION}",
+				header.format_version
+			));
+		}
+
+		Ok(Self {
+			name: name.to_string(),
+			directory,
+			header: migrate_header(header),
+		})
+	}
+
+	pub fn create(name: &str, header: WorldHeader) -> Result<Self, String> {
+		if Self::exists(name) {
+			return Err(format!("World \"{name}\" already exists"));
+		}
+
+		let save = Self {
+			name: name.to_string(),
+			directory: Self::directory_for(name),
+			header,
+		};
+
+		save.write_header()?;
+		Ok(save)
+	}
+
+	pub fn chunk_storage(&self) -> ChunkStorage {
+		ChunkStorage::new(self.directory.join(CHUNK_DIRECTORY_NAME))
+	}
+
+	fn directory_for(name: &str) -> PathBuf {
+		PathBuf::from(SAVES_DIRECTORY).join(name)
+	}
+
+	fn write_header(&self) -> Result<(), String> {
+		fs::create_dir_all(&self.directory)
+			.map_err(|e| format!("Failed to create {}: {e}", self.directory.display()))?;
+
+		let contents = ron::ser::to_string_pretty(&self.header, ron::ser::PrettyConfig::default())
+			.map_err(|e| format!("Failed to serialize world header: {e}"))?;
+
+		let path = self.directory.join(HEADER_FILE_NAME);
+
+		fs::write(&path, contents).map_err(|e| format!("Failed to write {}: {e}", path.display()))
+	}
+}
+
+/// Upgrades a header written by an older build. Per-tile changes are migrated separately when
+/// chunk files are decoded, see `persistence::migrate_tile_tag`.
+fn migrate_header(header: WorldHeader) -> WorldHeader {
+	WorldHeader {
+		format_version: SAVE_FORMAT_VERSION,
+		..header
+	}
+}
+
+fn save_world(
+	mut ev_save: EventReader<SaveWorldEvent>,
+	mut ev_exit: EventReader<AppExit>,
+	mut save: ResMut<WorldSave>,
+	mut map: ResMut<Map>,
+	storage: Res<ChunkStorage>,
+	ticktimer: Res<TickTimer>,
+	q_player: Query<(&Player, &Position, &Velocity)>,
+) {
+	let requested = ev_save.read().count() > 0;
+	let exiting = ev_exit.read().count() > 0;
+
+	if !requested && !exiting {
+		return;
+	}
+
+	save.header.tick = ticktimer.1;
+
+	for (player, position, velocity) in q_player.iter() {
+		if let Player::Local = player {
+			save.header.player_position = (position.0.x, position.0.y);
+			save.header.player_velocity = (velocity.x, velocity.y);
+		}
+	}
+
+	if let Err(e) = save.write_header() {
+		println!("World save error: {e}");
+	}
+
+	for (chunk_pos, chunk) in map.iter_mut() {
+		if chunk.modified {
+			storage.save_chunk(IVec2::new(chunk_pos.0, chunk_pos.1), chunk);
+			chunk.modified = false;
+		}
+	}
+
+	println!("Saved world \"{}\"", save.name);
+}
+
+#[derive(Event)]
+pub struct SaveWorldEvent;
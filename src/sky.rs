@@ -0,0 +1,238 @@
+use crate::{
+	grid::{ChunkTier, Coordinate, Map, MapTile},
+	light::LightingUpdateEvent,
+	worldgen::WorldGenerator,
+	TickEvent, CHUNK_SIZE,
+};
+use bevy::{
+	color::{Mix, Srgba},
+	prelude::{App, ClearColor, EventReader, Local, Plugin, Res, ResMut, Resource, Update},
+	utils::HashSet,
+};
+use std::f32::consts::TAU;
+
+/// Ticks from one midnight to the next, 10 minutes at 20 ticks per second.
+const DAY_LENGTH: u64 = 12000;
+/// Ticks into the day at which a new world starts, mid morning.
+const DAY_START: u64 = DAY_LENGTH / 3;
+/// `Daylight` at night, so that open areas can still be made out.
+const NIGHT_DAYLIGHT: u8 = 24;
+/// How many steps `Daylight` takes between night and day. Every step redraws the rendered
+/// chunks, so it doesn't change every tick.
+const DAYLIGHT_STEPS: f32 = 16.0;
+const DAY_SKY: Srgba = Srgba::rgb(0.45, 0.70, 0.95);
+const NIGHT_SKY: Srgba = Srgba::rgb(0.02, 0.03, 0.08);
+
+/// Light coming down from the sky, and the day/night cycle scaling it.
+///
+/// Sky light enters every column at the top of the loaded map, if the tiles above it were
+/// generated above the surface, and goes down the column losing whatever each tile's opacity
+/// stops. It doesn't spread sideways, so open columns are lit and caves stay dark.
+pub struct Sky;
+
+impl Plugin for Sky {
+	fn build(&self, app: &mut App) {
+		app.insert_resource(Daylight(u8::MAX))
+			.add_systems(Update, (advance_day, sky_light_update));
+	}
+}
+
+/// How bright the sky is right now, from `NIGHT_DAYLIGHT` at night to 255 during the day.
+#[derive(Resource)]
+pub struct Daylight(pub u8);
+
+fn advance_day(
+	mut tick: EventReader<TickEvent>,
+	mut daylight: ResMut<Daylight>,
+	mut map: ResMut<Map>,
+	clear_color: Option<ResMut<ClearColor>>,
+) {
+	let t = match tick.read().last() {
+		Some(v) => v.0,
+		None => return,
+	};
+
+	let day = time_of_day(t);
+	let level = daylight_level(day);
+
+	if daylight.0 != level {
+		daylight.0 = level;
+
+		for chunk in map.values_mut() {
+			if chunk.tier == ChunkTier::Rendered {
+				chunk.redraw_all();
+			}
+		}
+	}
+
+	if let Some(mut clear_color) = clear_color {
+		clear_color.0 = NIGHT_SKY.mix(&DAY_SKY, day).into();
+	}
+}
+
+/// How far into the day `tick` is, from 0.0 in the night to 1.0 during the day. The sun stays
+/// fully up or down for a while around noon and midnight.
+fn time_of_day(tick: u64) -> f32 {
+	let time = ((tick + DAY_START) % DAY_LENGTH) as f32 / DAY_LENGTH as f32;
+
+	(0.5 - (time * TAU).cos() * 0.8).clamp(0.0, 1.0)
+}
+
+fn daylight_level(day: f32) -> u8 {
+	let day = (day * DAYLIGHT_STEPS).round() / DAYLIGHT_STEPS;
+
+	NIGHT_DAYLIGHT + ((u8::MAX - NIGHT_DAYLIGHT) as f32 * day) as u8
+}
+
+fn sky_light_update(
+	mut map: ResMut<Map>,
+	generator: Res<WorldGenerator>,
+	mut ev_update: EventReader<LightingUpdateEvent>,
+	mut loaded_chunks: Local<HashSet<(i32, i32)>>,
+) {
+	let (w, h) = (CHUNK_SIZE.0 as i32, CHUNK_SIZE.1 as i32);
+	let mut columns = vec![];
+
+	// new chunks are lit from their top, and the chunks below unloaded ones are lit again as
+	// if the sky reached them
+	let chunks = map.keys().copied().collect::<HashSet<_>>();
+
+	for (x, y) in chunks.difference(&loaded_chunks) {
+		columns.extend((0..w).map(|i| (x * w + i, y * h + h - 1)));
+	}
+
+	for (x, y) in loaded_chunks.difference(&chunks) {
+		columns.extend((0..w).map(|i| (x * w + i, y * h - 1)));
+	}
+
+	*loaded_chunks = chunks;
+
+	for ev in ev_update.read() {
+		columns.push((ev.0.x_i32(), ev.0.y_i32()));
+	}
+
+	for (x, y) in columns {
+		light_column(&mut map, &generator, x, y);
+	}
+}
+
+/// Sets the sky light of the tiles from (x, y) downwards, until the tiles below are already
+/// lit the same way or aren't loaded.
+fn light_column(map: &mut Map, generator: &WorldGenerator, x: i32, y: i32) {
+	let mut sky_light = match map.get_tile(Coordinate::Tile { x, y: y + 1 }) {
+		Some(t) => transmitted(&t),
+		None if y + 1 > generator.surface_at(x) => u8::MAX,
+		None => 0,
+	};
+
+	for (i, y) in (i32::MIN..=y).rev().enumerate() {
+		let c = Coordinate::Tile { x, y };
+		let chunk = match map.get_chunk_mut(c) {
+			Some(v) => v,
+			None => break,
+		};
+		let chunklocal_coord = c.as_chunklocal_coord();
+		let t = chunk.tile_mut(chunklocal_coord.x_u8(), chunklocal_coord.y_u8());
+
+		let unchanged = t.sky_light == sky_light && t.lit;
+
+		// the first tile may have changed how much light it lets through, below it everything
+		// is as it was
+		if unchanged && i > 0 {
+			break;
+		}
+
+		t.sky_light = sky_light;
+		t.lit = true;
+		sky_light = transmitted(t);
+
+		if !unchanged {
+			chunk.mark_redraw(c);
+		}
+	}
+}
+
+/// Sky light passing through a tile.
+fn transmitted(maptile: &MapTile) -> u8 {
+	let opacity = maptile.tile_type.get_opacity() as u16;
+	(maptile.sky_light as u16 * (u8::MAX as u16 - opacity) / u8::MAX as u16) as u8
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{daylight_level, time_of_day, DAY_LENGTH, DAY_START, NIGHT_DAYLIGHT};
+	use crate::{
+		saves::WorldHeader,
+		testing::TestWorld,
+		tiletypes::TileType,
+		worldgen::{WorldGenerator, DEFAULT_SEED},
+	};
+
+	#[test]
+	fn open_columns_are_lit_by_the_sky() {
+		let mut world = TestWorld::new(
+			"
+			........
+			..####..
+			..#..#..
+			..####..
+			",
+		);
+
+		// the test world is buried in dirt, so a shaft is dug up to the top of the loaded chunks
+		world.fill((0, 4), (7, 63), TileType::EMPTY);
+
+		assert_eq!(world.sky_light(0, 0), u8::MAX);
+		assert_eq!(world.sky_light(3, 2), u8::MAX);
+		assert_eq!(world.sky_light(3, 1), 0);
+		assert_eq!(world.sky_light(0, -1), u8::MAX);
+		assert_eq!(world.sky_light(0, -2), 0);
+
+		world.set_tile(3, 2, TileType::EMPTY);
+		assert_eq!(world.sky_light(3, 1), u8::MAX);
+		assert_eq!(world.sky_light(3, 0), u8::MAX);
+
+		// water dims the light below it
+		world.set_tile(3, 2, TileType::named("water"));
+		let dimmed = world.sky_light(3, 1);
+		assert!(dimmed > 0 && dimmed < u8::MAX);
+
+		world.set_tile(3, 2, TileType::named("dirt"));
+		assert_eq!(world.sky_light(3, 1), 0);
+	}
+
+	#[test]
+	fn daylight_follows_the_time_of_day() {
+		let midnight = DAY_LENGTH - DAY_START;
+		let noon = midnight + DAY_LENGTH / 2;
+
+		assert_eq!(daylight_level(time_of_day(midnight)), NIGHT_DAYLIGHT);
+		assert_eq!(daylight_level(time_of_day(noon)), u8::MAX);
+
+		let sunrise = daylight_level(time_of_day(midnight + DAY_LENGTH / 4));
+		assert!(sunrise > NIGHT_DAYLIGHT && sunrise < u8::MAX);
+		assert!(time_of_day(0) > time_of_day(midnight));
+	}
+
+	#[test]
+	fn older_worlds_keep_their_terrain() {
+		let header: WorldHeader = ron::from_str(
+			"(format_version: 1, seed: 1337, tick: 0, player_position: (0.0, 0.0), \
+			player_velocity: (0.0, 0.0))",
+		)
+		.unwrap();
+
+		assert!(!header.open_sky);
+		assert!(WorldHeader::default().open_sky);
+
+		// terrain goes on above the surface of newer worlds
+		let generator = WorldGenerator::from_seed(DEFAULT_SEED);
+		let old = generator.clone().with_open_sky(false);
+
+		assert!((0..64).any(|x| {
+			let y = generator.surface_at(x) + 4;
+			generator.tiletype_at(x, y) == TileType::EMPTY
+				&& old.tiletype_at(x, y) != TileType::EMPTY
+		}));
+	}
+}
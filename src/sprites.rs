@@ -1,24 +1,24 @@
-use crate::tiletypes::TileType;
+use crate::tiletypes::{asset_path, registry};
 use bevy::{
 	prelude::{AssetServer, Commands, Handle, Image, Res, Resource},
 	text::Font,
 	utils::HashMap,
 };
 use std::fs::read_dir;
-use strum::IntoEnumIterator;
 
 pub fn setup_sprites(mut commands: Commands, asset_server: Res<AssetServer>) {
 	let mut tiles = HashMap::new();
 
-	for tile_type in TileType::iter() {
-		if !tile_type.is_visible() {
-			continue;
-		}
+	for tile_type in registry().iter() {
+		let folder = match registry().sprite(tile_type) {
+			Some(v) => v,
+			None => continue,
+		};
 
 		let mut images = vec![];
 		let tilename = tile_type.to_string();
 
-		let mut file_names: Vec<String> = read_dir(format!("assets/tiles/{tilename}"))
+		let mut file_names: Vec<String> = read_dir(asset_path(&format!("tiles/{folder}")))
 			.unwrap()
 			.filter_map(|entry| entry.ok())
 			.filter_map(|entry| entry.file_name().to_str().map(|s| s.to_string()))
@@ -31,7 +31,7 @@ pub fn setup_sprites(mut commands: Commands, asset_server: Res<AssetServer>) {
 		});
 
 		for filename in file_names {
-			images.push(asset_server.load(format!("tiles/{tilename}/{filename}")));
+			images.push(asset_server.load(format!("tiles/{folder}/{filename}")));
 		}
 
 		tiles.insert(tilename, images);
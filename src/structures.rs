@@ -0,0 +1,285 @@
+use crate::{
+	grid::Coordinate,
+	tiletypes::{known_tiles, TileType},
+	worldgen::{chunk_index, WorldGenerator},
+	CHUNK_SIZE,
+};
+use bevy::{
+	prelude::{IVec2, Resource},
+	utils::{HashMap, HashSet},
+};
+
+/// Width and height of a structure region, in chunks.
+const REGION_SIZE: i32 = 4;
+
+/// How many tiles a feature may extend past the region it was seeded in. Must stay below the
+/// region size in tiles, so that only a chunk's neighboring regions can write into it.
+const MAX_FEATURE_REACH: i32 = 96;
+
+const MAX_FEATURES_PER_REGION: u32 = 5;
+const FEATURE_COUNT_SALT: u32 = 0x100;
+const FEATURE_SALT: u32 = 0x200;
+
+/// Buffers the tiles that structure features write into chunks which haven't been generated yet.
+/// Features are seeded per region, and a region is expanded the first time any chunk it can reach
+/// is generated, so every write into a chunk is known before that chunk is.
+#[derive(Resource, Default)]
+pub struct StructureWrites {
+	generated_regions: HashSet<(i32, i32)>,
+	pending: HashMap<(i32, i32), Vec<PendingWrite>>,
+}
+
+struct PendingWrite {
+	order: (i32, i32, u32, u32),
+	chunklocal: (u8, u8),
+	write: TileWrite,
+}
+
+#[derive(Clone, Copy)]
+struct TileWrite {
+	tile_type: TileType,
+	solid_only: bool,
+}
+
+enum Feature {
+	Tunnel {
+		from: IVec2,
+		to: IVec2,
+		radius: i32,
+	},
+	Vein {
+		center: IVec2,
+		radius: i32,
+		tile_type: TileType,
+		seed: u32,
+	},
+	Ruin {
+		origin: IVec2,
+		width: i32,
+		height: i32,
+	},
+}
+
+impl StructureWrites {
+	/// Applies every structure write that lands in `chunk_pos` on top of its freshly
+	/// generated `tiles`. Writes are applied in region and feature order rather than in
+	/// the order regions were expanded, so the result doesn't depend on chunk load order.
+	/// Returns whether there were any. They aren't buffered anymore afterwards, so the chunk
+	/// has to be saved to keep them.
+	pub fn apply(
+		&mut self,
+		generator: &WorldGenerator,
+		chunk_pos: IVec2,
+		tiles: &mut [TileType],
+	) -> bool {
+		let region = region_of(chunk_pos);
+
+		for x in -1..=1 {
+			for y in -1..=1 {
+				self.generate_region(generator, region + IVec2::new(x, y));
+			}
+		}
+
+		let mut writes = match self.pending.remove(&(chunk_pos.x, chunk_pos.y)) {
+			Some(v) => v,
+			None => return false,
+		};
+
+		writes.sort_by_key(|w| w.order);
+
+		for w in writes {
+			let i = chunk_index(w.chunklocal.0 as i32, w.chunklocal.1 as i32);
+
+			if !w.write.solid_only || tiles[i].is_solid() {
+				tiles[i] = w.write.tile_type;
+			}
+		}
+
+		true
+	}
+
+	/// Drops the writes buffered for a chunk that was loaded from disk, since the saved
+	/// tiles already contain them.
+	pub fn discard(&mut self, chunk_pos: IVec2) {
+		self.pending.remove(&(chunk_pos.x, chunk_pos.y));
+	}
+
+	fn generate_region(&mut self, generator: &WorldGenerator, region: IVec2) {
+		if !self.generated_regions.insert((region.x, region.y)) {
+			return;
+		}
+
+		for (feature_index, feature) in features_in_region(generator, region).iter().enumerate() {
+			for (write_index, (tile, write)) in feature.writes().into_iter().enumerate() {
+				let coord = Coordinate::Tile {
+					x: tile.x,
+					y: tile.y,
+				};
+				let chunk_coord = coord.as_chunk_coord();
+				let chunklocal_coord = coord.as_chunklocal_coord();
+
+				self.pending
+					.entry((chunk_coord.x_i32(), chunk_coord.y_i32()))
+					.or_default()
+					.push(PendingWrite {
+						order: (region.x, region.y, feature_index as u32, write_index as u32),
+						chunklocal: (chunklocal_coord.x_u8(), chunklocal_coord.y_u8()),
+						write,
+					});
+			}
+		}
+	}
+}
+
+impl Feature {
+	fn writes(&self) -> Vec<(IVec2, TileWrite)> {
+		let mut writes = vec![];
+
+		match self {
+			Feature::Tunnel { from, to, radius } => {
+				let mut carved = HashSet::new();
+				let from = Coordinate::Tile {
+					x: from.x,
+					y: from.y,
+				};
+				let to = Coordinate::Tile { x: to.x, y: to.y };
+
+				for c in from.raycast_to(to) {
+					for x in -radius..=*radius {
+						for y in -radius..=*radius {
+							if x * x + y * y > radius * radius {
+								continue;
+							}
+
+							let tile = IVec2::new(c.x_i32() + x, c.y_i32() + y);
+
+							if carved.insert((tile.x, tile.y)) {
+								writes.push((
+									tile,
+									TileWrite {
+										tile_type: TileType::EMPTY,
+										solid_only: false,
+									},
+								));
+							}
+						}
+					}
+				}
+			}
+			Feature::Vein {
+				center,
+				radius,
+				tile_type,
+				seed,
+			} => {
+				for x in -radius..=*radius {
+					for y in -radius..=*radius {
+						// roughen the edge so veins aren't perfect circles
+						let edge = (seed.wrapping_add((x * 31 + y) as u32) % 3) as i32;
+
+						if x * x + y * y > radius * radius - edge {
+							continue;
+						}
+
+						writes.push((
+							*center + IVec2::new(x, y),
+							TileWrite {
+								tile_type: *tile_type,
+								solid_only: true,
+							},
+						));
+					}
+				}
+			}
+			Feature::Ruin {
+				origin,
+				width,
+				height,
+			} => {
+				for x in 0..*width {
+					for y in 0..*height {
+						let wall = x == 0 || x == width - 1 || y == 0 || y == height - 1;
+						let doorway = (x == 0 || x == width - 1) && (y == 1 || y == 2);
+
+						let tile_type = if x == width / 2 && y == height - 2 {
+							known_tiles().lantern
+						} else if wall && !doorway {
+							known_tiles().gravel
+						} else {
+							TileType::EMPTY
+						};
+
+						writes.push((
+							*origin + IVec2::new(x, y),
+							TileWrite {
+								tile_type,
+								solid_only: false,
+							},
+						));
+					}
+				}
+			}
+		}
+
+		writes
+	}
+}
+
+fn features_in_region(generator: &WorldGenerator, region: IVec2) -> Vec<Feature> {
+	let region_width = REGION_SIZE * CHUNK_SIZE.0 as i32;
+	let region_height = REGION_SIZE * CHUNK_SIZE.1 as i32;
+	let origin = IVec2::new(region.x * region_width, region.y * region_height);
+	let count = generator.hash(region.x, region.y, FEATURE_COUNT_SALT) % MAX_FEATURES_PER_REGION;
+	let mut features = vec![];
+
+	for i in 0..count {
+		let rand = |salt: u32| generator.hash(region.x, region.y, FEATURE_SALT + i * 16 + salt);
+
+		let position = origin
+			+ IVec2::new(
+				(rand(0) % region_width as u32) as i32,
+				(rand(1) % region_height as u32) as i32,
+			);
+
+		let feature = match rand(2) % 3 {
+			0 => {
+				let reach = MAX_FEATURE_REACH - 2;
+				let offset = IVec2::new(
+					(rand(3) % (reach as u32 * 2)) as i32 - reach,
+					(rand(4) % reach as u32) as i32 - reach / 2,
+				);
+
+				Feature::Tunnel {
+					from: position,
+					to: position + offset,
+					radius: 1 + (rand(5) % 2) as i32,
+				}
+			}
+			1 => match generator.biome_at(position.x, position.y).vein_tile() {
+				Some(tile_type) => Feature::Vein {
+					center: position,
+					radius: 2 + (rand(3) % 5) as i32,
+					tile_type,
+					seed: rand(4),
+				},
+				None => continue,
+			},
+			_ => Feature::Ruin {
+				origin: position,
+				width: 8 + (rand(3) % 7) as i32,
+				height: 6 + (rand(4) % 4) as i32,
+			},
+		};
+
+		features.push(feature);
+	}
+
+	features
+}
+
+fn region_of(chunk_pos: IVec2) -> IVec2 {
+	IVec2::new(
+		chunk_pos.x.div_euclid(REGION_SIZE),
+		chunk_pos.y.div_euclid(REGION_SIZE),
+	)
+}
@@ -0,0 +1,387 @@
+//! Deterministic test harness for the tile simulation. A `TestWorld` is a windowless `App`
+//! running the grid, physics, light and sky plugins, with a layout stamped into the map from ASCII
+//! art.
+//! Ticks are only advanced explicitly, so every run of a test simulates the same steps.
+//!
+//! Legend: `.` empty, `#` dirt, `M` moss, `G` gravel, `S` sand, `W`/`w` full/half water,
+//! `A`/`a` full/half magma, `O`/`o` full/half oil, `L` lantern, `X` obsidian, `F` fire,
+//! `~` steam, `%` smoke.
+//! Everything outside the art is filled with dirt.
+
+use crate::{
+	grid::{insert_chunk, spawn_chunk, ChunkTier, ChunkTiles, Coordinate, Grid, Map},
+	light::{AddLightSourceEvent, Light, LightBackend, LightingUpdateEvent},
+	liquidcheck::{LiquidCheck, LiquidDrift, LiquidLedger},
+	persistence::ChunkStorage,
+	sky::Sky,
+	structures::StructureWrites,
+	tilephysics::{SimulationStats, TilePhysics, UpdateTileEvent},
+	tiles::set_tile,
+	tiletypes::{Liquid, TileType},
+	worldgen::{WorldGenerator, DEFAULT_SEED},
+	TickEvent, TickTimer, CHUNK_SIZE,
+};
+use bevy::{
+	ecs::system::RunSystemOnce,
+	prelude::{App, Commands, EventWriter, IVec2, MinimalPlugins, Res, ResMut, Timer, TimerMode},
+};
+use std::env;
+
+const HALF_LEVEL: u8 = u8::MAX / 2 + 1;
+
+pub struct TestWorld {
+	app: App,
+	width: i32,
+	height: i32,
+	tick: u64,
+}
+
+impl TestWorld {
+	/// Builds a world with `layout` stamped so that its bottom left character is tile (0, 0).
+	/// The chunk containing the layout and all its neighbors are loaded, but only the chunk
+	/// containing the layout is simulated.
+	pub fn new(layout: &str) -> Self {
+		Self::with_light_backend(layout, LightBackend::default())
+	}
+
+	/// Builds a world like `new`, lit by `light_backend`.
+	pub fn with_light_backend(layout: &str, light_backend: LightBackend) -> Self {
+		let rows = parse_layout(layout);
+		let height = rows.len() as i32;
+		let width = rows.iter().map(|r| r.len()).max().unwrap_or(0) as i32;
+
+		assert!(
+			width <= CHUNK_SIZE.0 as i32 && height <= CHUNK_SIZE.1 as i32,
+			"Test layouts must fit in a single chunk"
+		);
+
+		let mut app = App::new();
+
+		app.add_plugins((MinimalPlugins, Grid, TilePhysics, Light, Sky, LiquidCheck))
+			.add_event::<TickEvent>()
+			.insert_resource(light_backend)
+			.insert_resource(TickTimer(Timer::from_seconds(1.0, TimerMode::Repeating), 0))
+			.insert_resource(WorldGenerator::from_seed(DEFAULT_SEED))
+			.insert_resource(ChunkStorage::new(
+				env::temp_dir().join("bevy-tilegame-base-tests-nonexistent"),
+			));
+
+		// there are no chunk loaders, so the chunks loaded here stay as they are
+		app.world_mut().run_system_once(
+			|mut commands: Commands,
+			 mut map: ResMut<Map>,
+			 storage: Res<ChunkStorage>,
+			 generator: Res<WorldGenerator>,
+			 mut structures: ResMut<StructureWrites>,
+			 mut ev_update: EventWriter<UpdateTileEvent>,
+			 mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
+			 mut ev_updatelighting: EventWriter<LightingUpdateEvent>| {
+				for x in -1..=1 {
+					for y in -1..=1 {
+						spawn_chunk(
+							&mut commands,
+							IVec2::new(x, y),
+							&mut map,
+							&storage,
+							&generator,
+							&mut structures,
+							&mut ev_update,
+							&mut ev_addlightsource,
+							&mut ev_updatelighting,
+						);
+					}
+				}
+
+				map.get_mut(&(0, 0)).unwrap().tier = ChunkTier::Simulated;
+			},
+		);
+
+		app.world_mut().run_system_once(
+			move |mut map: ResMut<Map>,
+			      mut ev_update: EventWriter<UpdateTileEvent>,
+			      mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
+			      mut ev_updatelighting: EventWriter<LightingUpdateEvent>| {
+				let min = -(CHUNK_SIZE.0 as i32);
+				let max = CHUNK_SIZE.0 as i32 * 2;
+
+				for x in min..max {
+					for y in min..max {
+						let tile_type = if (0..width).contains(&x) && (0..height).contains(&y) {
+							let row = &rows[(height - 1 - y) as usize];
+							tiletype_from_char(*row.get(x as usize).unwrap_or(&'#'))
+						} else {
+							TileType::named("dirt")
+						};
+
+						set_tile(
+							Coordinate::Tile { x, y },
+							tile_type,
+							&mut map,
+							&mut ev_update,
+							&mut ev_addlightsource,
+							&mut ev_updatelighting,
+							None,
+						);
+					}
+				}
+			},
+		);
+
+		// let the stamped tiles wake up before the first tick
+		app.update();
+
+		Self {
+			app,
+			width,
+			height,
+			tick: 0,
+		}
+	}
+
+	/// Advances the simulation by `n` ticks.
+	pub fn tick(&mut self, n: u64) {
+		for _ in 0..n {
+			self.tick += 1;
+			self.app.world_mut().resource_mut::<TickTimer>().1 = self.tick;
+			self.app.world_mut().send_event(TickEvent(self.tick));
+			self.app.update();
+		}
+	}
+
+	/// Places a tile anywhere in the loaded chunks, including outside of the layout.
+	pub fn set_tile(&mut self, x: i32, y: i32, tile_type: TileType) {
+		self.fill((x, y), (x, y), tile_type);
+	}
+
+	/// Fills the rectangle from `min` to `max`, both inclusive, with `tile_type`.
+	pub fn fill(&mut self, min: (i32, i32), max: (i32, i32), tile_type: TileType) {
+		self.app.world_mut().run_system_once(
+			move |mut map: ResMut<Map>,
+			      mut ev_update: EventWriter<UpdateTileEvent>,
+			      mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
+			      mut ev_updatelighting: EventWriter<LightingUpdateEvent>| {
+				for x in min.0..=max.0 {
+					for y in min.1..=max.1 {
+						set_tile(
+							Coordinate::Tile { x, y },
+							tile_type,
+							&mut map,
+							&mut ev_update,
+							&mut ev_addlightsource,
+							&mut ev_updatelighting,
+							None,
+						);
+					}
+				}
+			},
+		);
+
+		self.app.update();
+	}
+
+	/// Lets the physics run in a loaded chunk other than the one containing the layout.
+	pub fn simulate_chunk(&mut self, x: i32, y: i32) {
+		if let Some(chunk) = self.app.world_mut().resource_mut::<Map>().get_mut(&(x, y)) {
+			chunk.tier = ChunkTier::Simulated;
+		}
+	}
+
+	/// Removes a chunk from the map without saving it.
+	pub fn unload_chunk(&mut self, x: i32, y: i32) {
+		self.app.world_mut().run_system_once(
+			move |mut commands: Commands, mut map: ResMut<Map>| {
+				if let Some(chunk) = map.remove(&(x, y)) {
+					commands.entity(chunk.entity).despawn();
+				}
+			},
+		);
+	}
+
+	/// Loads a chunk as if its save only held `tile_type` tiles.
+	pub fn load_chunk(&mut self, x: i32, y: i32, tile_type: TileType) {
+		self.app.world_mut().run_system_once(
+			move |mut commands: Commands,
+			      mut map: ResMut<Map>,
+			      generator: Res<WorldGenerator>,
+			      mut structures: ResMut<StructureWrites>,
+			      mut ev_update: EventWriter<UpdateTileEvent>,
+			      mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
+			      mut ev_updatelighting: EventWriter<LightingUpdateEvent>| {
+				insert_chunk(
+					&mut commands,
+					IVec2::new(x, y),
+					ChunkTiles::filled(tile_type),
+					&mut map,
+					&generator,
+					&mut structures,
+					&mut ev_update,
+					&mut ev_addlightsource,
+					&mut ev_updatelighting,
+				);
+			},
+		);
+	}
+
+	/// Tile at a layout position, with (0, 0) being the bottom left character.
+	pub fn tile(&self, x: i32, y: i32) -> TileType {
+		self.app
+			.world()
+			.resource::<Map>()
+			.get_tile(Coordinate::Tile { x, y })
+			.map(|t| t.tile_type)
+			.unwrap_or(TileType::EMPTY)
+	}
+
+	/// Light level of a tile anywhere in the loaded chunks, that of its brightest channel. 0 if
+	/// no light reached it.
+	pub fn light_level(&self, x: i32, y: i32) -> u8 {
+		self.app
+			.world()
+			.resource::<Map>()
+			.get_tile(Coordinate::Tile { x, y })
+			.and_then(|t| t.light.into_iter().max())
+			.unwrap_or(0)
+	}
+
+	/// Light of a tile in each channel, see `light_level`.
+	pub fn light(&self, x: i32, y: i32) -> [u8; 3] {
+		self.app
+			.world()
+			.resource::<Map>()
+			.get_tile(Coordinate::Tile { x, y })
+			.map(|t| t.light)
+			.unwrap_or([0; 3])
+	}
+
+	/// Light the sky gives a tile at noon, see `sky`.
+	pub fn sky_light(&self, x: i32, y: i32) -> u8 {
+		self.app
+			.world()
+			.resource::<Map>()
+			.get_tile(Coordinate::Tile { x, y })
+			.map(|t| t.sky_light)
+			.unwrap_or(0)
+	}
+
+	/// Current state of the stamped area, in the same format as the layout it was built from.
+	pub fn layout(&self) -> String {
+		let mut rows = vec![];
+
+		for y in (0..self.height).rev() {
+			rows.push(
+				(0..self.width)
+					.map(|x| char_from_tiletype(self.tile(x, y)))
+					.collect::<String>(),
+			);
+		}
+
+		rows.join("\n")
+	}
+
+	pub fn assert_layout(&self, expected: &str) {
+		let expected = parse_layout(expected)
+			.into_iter()
+			.map(|r| r.into_iter().collect::<String>())
+			.collect::<Vec<_>>()
+			.join("\n");
+		let actual = self.layout();
+
+		assert!(
+			actual == expected,
+			"Layout after {} ticks doesn't match.\nexpected:\n{expected}\nactual:\n{actual}\n",
+			self.tick
+		);
+	}
+
+	/// Work done by the tile simulation on the last tick.
+	pub fn stats(&self) -> SimulationStats {
+		*self.app.world().resource::<SimulationStats>()
+	}
+
+	/// Every liquid conservation violation the checker has seen so far.
+	pub fn liquid_drifts(&self) -> &[LiquidDrift] {
+		&self.app.world().resource::<LiquidLedger>().drifts
+	}
+
+	/// Sum of the levels of every liquid tile in the stamped area that matches `liquid`'s type.
+	pub fn total_level(&self, liquid: TileType) -> u32 {
+		let mut total = 0;
+
+		for x in 0..self.width {
+			for y in 0..self.height {
+				let t = self.tile(x, y);
+
+				if t.id() == liquid.id() {
+					total += t.liquid().level as u32;
+				}
+			}
+		}
+
+		total
+	}
+}
+
+fn parse_layout(layout: &str) -> Vec<Vec<char>> {
+	layout
+		.lines()
+		.map(|l| l.trim())
+		.filter(|l| !l.is_empty())
+		.map(|l| l.chars().collect())
+		.collect()
+}
+
+fn tiletype_from_char(c: char) -> TileType {
+	let liquid = |name, level| {
+		TileType::named(name).with_liquid(Liquid {
+			level,
+			..Default::default()
+		})
+	};
+
+	match c {
+		'.' => TileType::EMPTY,
+		'#' => TileType::named("dirt"),
+		'M' => TileType::named("moss"),
+		'G' => TileType::named("gravel"),
+		'S' => TileType::named("sand"),
+		'W' => liquid("water", u8::MAX),
+		'w' => liquid("water", HALF_LEVEL),
+		'A' => liquid("magma", u8::MAX),
+		'a' => liquid("magma", HALF_LEVEL),
+		'O' => liquid("oil", u8::MAX),
+		'o' => liquid("oil", HALF_LEVEL),
+		'L' => TileType::named("lantern"),
+		'X' => TileType::named("obsidian"),
+		'F' => TileType::named("fire"),
+		'~' => TileType::named("steam"),
+		'%' => TileType::named("smoke"),
+		_ => panic!("Unknown layout character: {c}"),
+	}
+}
+
+fn char_from_tiletype(tile_type: TileType) -> char {
+	let full = |full, partial| {
+		if tile_type.liquid().level == u8::MAX {
+			full
+		} else {
+			partial
+		}
+	};
+
+	match tile_type.name() {
+		"empty" => '.',
+		"dirt" => '#',
+		"moss" => 'M',
+		"gravel" => 'G',
+		"sand" => 'S',
+		"water" => full('W', 'w'),
+		"magma" => full('A', 'a'),
+		"oil" => full('O', 'o'),
+		"lantern" => 'L',
+		"obsidian" => 'X',
+		"fire" => 'F',
+		"steam" => '~',
+		"smoke" => '%',
+		name => panic!("No layout character for tile: {name}"),
+	}
+}
@@ -1,35 +1,45 @@
 use crate::{
-	grid::{xorshift_from_coord, Coordinate, CreateTileEvent, Map, MapTile},
+	grid::{
+		xorshift_from_coord, ActiveTiles, Chunk, Coordinate, CreateTileEvent, Map, MapChunk,
+		MapTile, ScanOrder,
+	},
 	light::{AddLightSourceEvent, LightingUpdateEvent},
-	sprites::Sprites,
 	tileoutline::ConnectedNeighbors,
-	tiles::{create_tile_spritebundle, set_tile, FallingTile, Tile, WeightedTile},
+	tiles::{write_tile, TileEventBuffer},
 	tiletypes::{Liquid, LiquidInteraction, TileType},
-	TickEvent, TickTimer,
+	TickEvent, TickTimer, CHUNK_SIZE,
 };
 use bevy::{
 	prelude::{
-		App, Commands, Component, Entity, Event, EventReader, EventWriter, Last, Plugin,
-		PostUpdate, Query, Res, ResMut, Transform, Update, Vec2, Vec3,
+		Added, App, Event, EventReader, EventWriter, IVec2, IntoSystemConfigs, Last, Plugin,
+		PostUpdate, Query, ResMut, Resource, Update, Vec2,
 	},
-	sprite::SpriteBundle,
+	tasks::ComputeTaskPool,
+	utils::{HashMap, HashSet},
 };
-use std::{mem::discriminant, panic};
+use std::{mem, panic};
 
 const INITIAL_LIQUID_MOMENTUM: u8 = 200;
 
-#[derive(Component)]
-pub struct FlowingTile {
-	x: i32,
-}
-
 pub struct TilePhysics;
 
 impl Plugin for TilePhysics {
 	fn build(&self, app: &mut App) {
 		app.add_event::<UpdateTileEvent>()
 			.add_event::<UpdateOutlineSpriteEvent>()
-			.add_systems(Update, (flow_liquid_tile, apply_gravity))
+			.add_event::<LiquidConsumedEvent>()
+			.init_resource::<WaitingTiles>()
+			.init_resource::<SimulationStats>()
+			.add_systems(
+				Update,
+				(
+					wake_waiting_tiles,
+					apply_gravity,
+					flow_liquid_tile,
+					simulate_gases,
+				)
+					.chain(),
+			)
 			.add_systems(PostUpdate, update_tile)
 			.add_systems(Last, update_outline_sprite_event);
 	}
@@ -39,8 +49,6 @@ pub fn update_tile(
 	mut map: ResMut<Map>,
 	mut ev_update: EventReader<UpdateTileEvent>,
 	mut ev_create_tile: EventWriter<CreateTileEvent>,
-	mut commands: Commands,
-	sprites: Res<Sprites>,
 ) {
 	for ev in ev_update.read() {
 		let tile = if let Some(t) = map.get_tile(ev.0) {
@@ -50,18 +58,30 @@ pub fn update_tile(
 		};
 
 		if tile.tile_type.is_weighted() {
-			commands
-				.entity(tile.tile_entity)
-				.insert(FallingTile { y: ev.0.y_i32() });
+			let chunklocal_coord = ev.0.as_chunklocal_coord();
+
+			if let Some(chunk) = map.get_chunk_mut(ev.0) {
+				chunk
+					.falling
+					.insert((chunklocal_coord.x_u8(), chunklocal_coord.y_u8()));
+			}
+		}
+
+		if tile.tile_type.get_lifetime() != 0 {
+			let chunklocal_coord = ev.0.as_chunklocal_coord();
+
+			if let Some(chunk) = map.get_chunk_mut(ev.0) {
+				chunk
+					.decaying
+					.insert((chunklocal_coord.x_u8(), chunklocal_coord.y_u8()));
+			}
 		}
 
-		update_outline_sprite(tile, &mut commands, &sprites, &mut map);
+		update_outline_sprite(tile, &mut map);
 
 		if let Ok(liquid) = tile.tile_type.get_liquid() {
 			let new_sprite_override = if let Some(above) = map.get_tile(ev.0.moved(&Vec2::Y)) {
-				above.tile_type.is_liquid()
-					&& std::mem::discriminant(&above.tile_type)
-						!= std::mem::discriminant(&tile.tile_type)
+				above.tile_type.is_liquid() && above.tile_type.id() != tile.tile_type.id()
 			} else {
 				false
 			};
@@ -80,26 +100,22 @@ pub fn update_tile(
 	}
 }
 
-fn update_outline_sprite_event(
+pub fn update_outline_sprite_event(
 	mut ev_update: EventReader<UpdateOutlineSpriteEvent>,
 	mut map: ResMut<Map>,
-	mut commands: Commands,
-	sprites: Res<Sprites>,
 ) {
 	for ev in ev_update.read() {
 		if let Some(maptile) = map.get_tile(ev.0) {
-			update_outline_sprite(maptile, &mut commands, &sprites, &mut map);
+			update_outline_sprite(maptile, &mut map);
 		}
 	}
 }
 
-fn update_outline_sprite(
-	maptile: MapTile,
-	commands: &mut Commands,
-	sprites: &Sprites,
-	map: &mut ResMut<Map>,
-) {
-	let outline_id = if !maptile.tile_type.is_visible() || maptile.tile_type.is_liquid() {
+fn update_outline_sprite(maptile: MapTile, map: &mut Map) {
+	let outline_id = if !maptile.tile_type.is_visible()
+		|| maptile.tile_type.is_liquid()
+		|| maptile.tile_type.is_gas()
+	{
 		40 // no outline
 	} else {
 		let mut connected = ConnectedNeighbors::new();
@@ -150,488 +166,803 @@ fn update_outline_sprite(
 		t.outline_id = outline_id;
 	}
 
-	commands
-		.entity(maptile.outline_entity)
-		.insert(SpriteBundle {
-			texture: sprites.tile_outlines.get(outline_id - 1).unwrap().clone(),
-			transform: Transform {
-				translation: Vec3 {
-					x: 0.0,
-					y: 0.0,
-					z: 1.0,
-				},
-				..Default::default()
-			},
-			..Default::default()
-		});
+	if let Some(chunk) = map.get_chunk_mut(maptile.tile_coord) {
+		chunk.mark_redraw(maptile.tile_coord);
+	}
+}
+
+/// How much work the tile simulation did on the last tick.
+#[derive(Resource, Default, Clone, Copy)]
+pub struct SimulationStats {
+	/// Tiles taken from the active sets by `apply_gravity`, `flow_liquid_tile` and
+	/// `simulate_gases`.
+	pub simulated_tiles: usize,
+	/// Simulated chunks with active tiles.
+	pub awake_chunks: usize,
+	/// Simulated chunks without active tiles, which weren't looked at.
+	pub sleeping_chunks: usize,
+}
+
+/// Tiles whose physics reached into a chunk that isn't loaded, by the position of that chunk.
+/// Falling tiles stop being simulated until then, flowing ones treat the chunk as solid.
+#[derive(Resource, Default)]
+pub struct WaitingTiles(HashMap<(i32, i32), HashSet<(i32, i32)>>);
+
+impl WaitingTiles {
+	fn wait_for(&mut self, unloaded: Coordinate, tile: Coordinate) {
+		let chunk_coord = unloaded.as_chunk_coord();
+		let tile_coord = tile.as_tile_coord();
+
+		self.0
+			.entry((chunk_coord.x_i32(), chunk_coord.y_i32()))
+			.or_default()
+			.insert((tile_coord.x_i32(), tile_coord.y_i32()));
+	}
+}
+
+/// Updates the tiles waiting for a chunk once it is loaded, so their physics resume.
+fn wake_waiting_tiles(
+	q_chunks: Query<&Chunk, Added<Chunk>>,
+	mut waiting: ResMut<WaitingTiles>,
+	mut ev_updatetile: EventWriter<UpdateTileEvent>,
+) {
+	for chunk in q_chunks.iter() {
+		if let Some(tiles) = waiting.0.remove(&(chunk.0.x, chunk.0.y)) {
+			for (x, y) in tiles {
+				ev_updatetile.send(UpdateTileEvent(Coordinate::Tile { x, y }));
+			}
+		}
+	}
 }
 
 fn apply_gravity(
-	mut q_falling_tile: Query<(Entity, &Tile, &WeightedTile, &FallingTile)>,
 	mut map: ResMut<Map>,
-	mut commands: Commands,
 	mut ev_updatetile: EventWriter<UpdateTileEvent>,
 	mut tick: EventReader<TickEvent>,
-	sprites: Res<Sprites>,
 	mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
 	mut ev_updatelighting: EventWriter<LightingUpdateEvent>,
+	mut waiting: ResMut<WaitingTiles>,
+	mut stats: ResMut<SimulationStats>,
 	ticktimer: ResMut<TickTimer>,
 ) {
 	for _ in tick.read() {
-		let mut tuples = vec![];
+		*stats = SimulationStats::default();
 
-		for (entity, tile, weighted_tile, falling_tile) in q_falling_tile.iter_mut() {
-			tuples.push((entity, tile, weighted_tile, falling_tile.y));
-		}
+		for (_, chunk) in map.iter() {
+			if !chunk.tier.is_simulated() {
+				continue;
+			}
 
-		if tuples.is_empty() {
-			return;
+			if chunk.is_asleep() {
+				stats.sleeping_chunks += 1;
+			} else {
+				stats.awake_chunks += 1;
+			}
 		}
 
-		tuples.sort_by(|a, b| a.3.cmp(&b.3));
+		let neighborhoods = simulate_phased(
+			&mut map,
+			|chunk| &mut chunk.falling,
+			ScanOrder::Rows,
+			|nb, tiles| fall(nb, tiles, ticktimer.1),
+		);
 
-		for tuple in tuples {
-			let maptile = if let Some(t) = map.get_tile(tuple.1.coord) {
+		for nb in neighborhoods {
+			stats.simulated_tiles += nb.simulated_tiles;
+
+			for (unloaded, tile) in nb.waiting {
+				waiting.wait_for(unloaded, tile);
+			}
+
+			nb.events.send(
+				&mut ev_updatetile,
+				&mut ev_addlightsource,
+				&mut ev_updatelighting,
+			);
+		}
+	}
+}
+
+fn fall(nb: &mut Neighborhood, tiles: Vec<(Coordinate, TileType)>, tick: u64) {
+	for (current_position, tile_type) in tiles {
+		// the tile may have been moved or changed earlier this tick, in which case
+		// the map holds the only up to date state
+		let maptile = if let Some(t) = nb.map.get_tile(current_position) {
+			if t.tile_type.id() == tile_type.id() {
 				t
 			} else {
 				continue;
-			};
+			}
+		} else {
+			continue;
+		};
 
-			let current_position = tuple.1.coord.as_tile_coord();
-
-			match get_fall_coord(&map, current_position, tuple.2.granularity, maptile) {
-				Ok(opt) => match opt {
-					Some(coord) => {
-						set_tile(
-							&mut commands,
-							current_position,
-							TileType::Empty,
-							&sprites,
-							&mut map,
-							&mut ev_updatetile,
-							&mut ev_addlightsource,
-							&mut ev_updatelighting,
-							None,
-						);
-
-						set_tile(
-							&mut commands,
-							coord,
-							tuple.1.tile_type,
-							&sprites,
-							&mut map,
-							&mut ev_updatetile,
-							&mut ev_addlightsource,
-							&mut ev_updatelighting,
-							Some(ticktimer.1 as i32 + coord.y_i32()),
-						);
-					}
-					None => {
-						commands
-							.entity(maptile.sprite_entity)
-							.insert(create_tile_spritebundle(
-								tuple.1.tile_type,
-								current_position,
-								&sprites,
-								None,
-							));
+		match get_fall_coord(
+			&nb.map,
+			current_position,
+			tile_type.get_granularity(),
+			maptile,
+		) {
+			Ok(opt) => match opt {
+				Some(coord) => {
+					// solids sink through liquids and gases, which swap into the vacated tile
+					let displaced = match nb.map.get_tile(coord) {
+						Some(t) if t.tile_type.is_liquid() || t.tile_type.is_gas() => t.tile_type,
+						_ => TileType::EMPTY,
+					};
 
-						let mut cmds = commands.entity(tuple.0);
-						cmds.remove::<FallingTile>();
+					nb.set_tile(current_position, displaced, None);
 
-						if maptile.tile_type.is_liquid() {
-							cmds.insert(FlowingTile {
-								x: current_position.x_i32(),
-							});
-						}
+					nb.set_tile(coord, maptile.tile_type, Some(tick as i32 + coord.y_i32()));
+				}
+				None => {
+					let chunklocal_coord = current_position.as_chunklocal_coord();
+					let key = (chunklocal_coord.x_u8(), chunklocal_coord.y_u8());
+					let chunk = nb.map.get_chunk_mut(current_position).unwrap();
 
-						continue;
+					chunk.falling.remove(key);
+
+					if maptile.tile_type.is_liquid() {
+						chunk.flowing.insert(key);
 					}
-				},
-				Err(()) => continue, // unloaded chunk
-			};
-		}
+
+					// landed tiles go back to the sprite variant of their coordinate
+					let t = chunk.tile_mut(key.0, key.1);
+
+					if t.texture_index.is_some() {
+						t.texture_index = None;
+						chunk.redraw.insert(key);
+					}
+
+					continue;
+				}
+			},
+			Err(unloaded) => {
+				let chunklocal_coord = current_position.as_chunklocal_coord();
+
+				if let Some(chunk) = nb.map.get_chunk_mut(current_position) {
+					chunk
+						.falling
+						.remove((chunklocal_coord.x_u8(), chunklocal_coord.y_u8()));
+				}
+
+				nb.wait_for(unloaded, current_position);
+			}
+		};
 	}
 }
 
 fn flow_liquid_tile(
 	mut tick: EventReader<TickEvent>,
 	mut map: ResMut<Map>,
-	mut commands: Commands,
 	mut ev_updatetile: EventWriter<UpdateTileEvent>,
-	sprites: Res<Sprites>,
-	q_flowing_tiles: Query<(Entity, &Tile, &FlowingTile)>,
 	mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
 	mut ev_updatelighting: EventWriter<LightingUpdateEvent>,
+	mut ev_consumed: EventWriter<LiquidConsumedEvent>,
+	mut waiting: ResMut<WaitingTiles>,
+	mut stats: ResMut<SimulationStats>,
 ) {
 	for t in tick.read() {
-		let mut tuples = vec![];
+		let order = ScanOrder::Columns {
+			right_to_left: t.0 % 2 == 1,
+		};
 
-		for (tile_entity, tile, flowing_tile) in q_flowing_tiles.iter() {
-			tuples.push((tile_entity, tile, flowing_tile.x));
-		}
+		let neighborhoods = simulate_phased(
+			&mut map,
+			|chunk| &mut chunk.flowing,
+			order,
+			|nb, tiles| flow(nb, tiles, t.0),
+		);
 
-		if t.0 % 2 == 0 {
-			tuples.sort_by(|a, b| a.2.cmp(&b.2));
-		} else {
-			tuples.sort_by(|a, b| b.2.cmp(&a.2));
-		}
+		for nb in neighborhoods {
+			stats.simulated_tiles += nb.simulated_tiles;
 
-		'outer: for tuple in tuples {
-			let tile_entity = tuple.0;
-			let tile = tuple.1;
+			for (unloaded, tile) in nb.waiting {
+				waiting.wait_for(unloaded, tile);
+			}
 
-			let maptile = if let Some(t) = map.get_tile(tile.coord) {
-				if t.tile_type.is_liquid() {
-					t
-				} else {
-					continue;
-				}
+			nb.events.send(
+				&mut ev_updatetile,
+				&mut ev_addlightsource,
+				&mut ev_updatelighting,
+			);
+			ev_consumed.send_batch(nb.consumed);
+		}
+	}
+}
+
+fn flow(nb: &mut Neighborhood, tiles: Vec<(Coordinate, TileType)>, tick: u64) {
+	'outer: for (tile_coord, _) in tiles {
+		let maptile = if let Some(t) = nb.map.get_tile(tile_coord) {
+			if t.tile_type.is_liquid() {
+				t
 			} else {
 				continue;
-			};
+			}
+		} else {
+			continue;
+		};
 
-			let fluidity = maptile.tile_type.get_fluidity();
+		let fluidity = maptile.tile_type.get_fluidity();
 
-			let maptile_liquid = if let Ok(v) = maptile.tile_type.get_liquid() {
-				v
-			} else {
-				commands.entity(tile_entity).remove::<FlowingTile>();
-				continue;
-			};
+		let maptile_liquid = if let Ok(v) = maptile.tile_type.get_liquid() {
+			v
+		} else {
+			stop_flowing(&mut nb.map, tile_coord);
+			continue;
+		};
 
-			let rand_bool = xorshift_from_coord(maptile.tile_coord) % 2 == 0;
-			let mut left_blocked = false;
-			let mut right_blocked = false;
+		let rand_bool = xorshift_from_coord(maptile.tile_coord) % 2 == 0;
+		let mut left_blocked = false;
+		let mut right_blocked = false;
 
-			for i in 0..=(fluidity * fluidity) as i32 {
-				for m in if rand_bool { [-1, 1] } else { [1, -1] } {
-					if (m == -1 && left_blocked) || (m == 1 && right_blocked) {
-						continue;
-					}
+		for i in 0..=(fluidity * fluidity) as i32 {
+			for m in if rand_bool { [-1, 1] } else { [1, -1] } {
+				if (m == -1 && left_blocked) || (m == 1 && right_blocked) {
+					continue;
+				}
 
-					let x = i * m;
+				let x = i * m;
 
-					if i == 0 && m == -1 {
-						continue;
-					}
+				if i == 0 && m == -1 {
+					continue;
+				}
 
-					let below_coord = maptile.tile_coord.moved(&Vec2::new(x as f32, -1.0));
+				let below_coord = maptile.tile_coord.moved(&Vec2::new(x as f32, -1.0));
 
-					if let Some(t) = map.get_tile(below_coord) {
-						if discriminant(&t.tile_type) == discriminant(&maptile.tile_type) {
-							let other_level = t.tile_type.liquid().level;
-							let other_emptiness = u8::MAX - other_level;
+				if let Some(t) = nb.map.get_tile(below_coord) {
+					if t.tile_type.id() == maptile.tile_type.id() {
+						let other_level = t.tile_type.liquid().level;
+						let other_emptiness = u8::MAX - other_level;
 
-							if other_emptiness != 0 {
-								let this_level = maptile_liquid.level;
+						if other_emptiness != 0 {
+							let this_level = maptile_liquid.level;
 
-								let this_remainder =
-									(other_level as i32 + this_level as i32) - u8::MAX as i32;
+							let this_remainder =
+								(other_level as i32 + this_level as i32) - u8::MAX as i32;
 
-								let (new_level, new_other_level) = if this_remainder < 0 {
-									(0, other_level + this_level)
-								} else {
-									(this_remainder as u8, u8::MAX)
-								};
-
-								set_tile(
-									&mut commands,
-									maptile.tile_coord,
-									if new_level != 0 {
-										maptile.tile_type.with_liquid(Liquid {
-											level: new_level,
-											..Default::default()
-										})
-									} else {
-										TileType::Empty
-									},
-									&sprites,
-									&mut map,
-									&mut ev_updatetile,
-									&mut ev_addlightsource,
-									&mut ev_updatelighting,
-									None,
-								);
-
-								set_tile(
-									&mut commands,
-									below_coord,
+							let (new_level, new_other_level) = if this_remainder < 0 {
+								(0, other_level + this_level)
+							} else {
+								(this_remainder as u8, u8::MAX)
+							};
+
+							nb.set_tile(
+								maptile.tile_coord,
+								if new_level != 0 {
 									maptile.tile_type.with_liquid(Liquid {
-										level: new_other_level,
+										level: new_level,
 										..Default::default()
-									}),
-									&sprites,
-									&mut map,
-									&mut ev_updatetile,
-									&mut ev_addlightsource,
-									&mut ev_updatelighting,
-									None,
-								);
+									})
+								} else {
+									TileType::EMPTY
+								},
+								None,
+							);
+
+							nb.set_tile(
+								below_coord,
+								maptile.tile_type.with_liquid(Liquid {
+									level: new_other_level,
+									..Default::default()
+								}),
+								None,
+							);
 
-								continue 'outer;
-							}
+							continue 'outer;
+						}
+					} else {
+						if m == -1 {
+							left_blocked = true;
 						} else {
-							if m == -1 {
-								left_blocked = true;
-							} else {
-								right_blocked = true;
-							}
-
-							if let Ok(t_liquid) = t.tile_type.get_liquid() {
-								let mut cont = true;
-
-								match maptile.tile_type.get_liquid_interaction_with(t.tile_type) {
-									LiquidInteraction::Vaporize => {
-										set_tile(
-											&mut commands,
-											maptile.tile_coord,
-											TileType::Empty,
-											&sprites,
-											&mut map,
-											&mut ev_updatetile,
-											&mut ev_addlightsource,
-											&mut ev_updatelighting,
-											None,
-										);
+							right_blocked = true;
+						}
 
-										set_tile(
-											&mut commands,
-											below_coord,
-											maptile.tile_type,
-											&sprites,
-											&mut map,
-											&mut ev_updatetile,
-											&mut ev_addlightsource,
-											&mut ev_updatelighting,
-											None,
-										);
-									}
-									LiquidInteraction::Vaporized => {
-										set_tile(
-											&mut commands,
-											maptile.tile_coord,
-											TileType::Empty,
-											&sprites,
-											&mut map,
-											&mut ev_updatetile,
-											&mut ev_addlightsource,
-											&mut ev_updatelighting,
-											None,
-										);
-									}
-									LiquidInteraction::Float => {
-										cont = false;
-										if !t_liquid.sprite_override {
-											set_tile(
-												&mut commands,
-												t.tile_coord,
-												t.tile_type.with_liquid(Liquid {
-													sprite_override: true,
-													..t_liquid
-												}),
-												&sprites,
-												&mut map,
-												&mut ev_updatetile,
-												&mut ev_addlightsource,
-												&mut ev_updatelighting,
-												None,
-											);
-										}
-									}
-									LiquidInteraction::Sink => {
-										set_tile(
-											&mut commands,
-											maptile.tile_coord,
-											t.tile_type,
-											&sprites,
-											&mut map,
-											&mut ev_updatetile,
-											&mut ev_addlightsource,
-											&mut ev_updatelighting,
-											None,
-										);
+						if let Ok(t_liquid) = t.tile_type.get_liquid() {
+							let mut cont = true;
 
-										set_tile(
-											&mut commands,
-											below_coord,
-											maptile.tile_type,
-											&sprites,
-											&mut map,
-											&mut ev_updatetile,
-											&mut ev_addlightsource,
-											&mut ev_updatelighting,
+							match maptile.tile_type.get_liquid_interaction_with(t.tile_type) {
+								LiquidInteraction::React { this, other } => {
+									react(nb, maptile, this, t, other);
+								}
+								LiquidInteraction::Float => {
+									cont = false;
+									if !t_liquid.sprite_override {
+										nb.set_tile(
+											t.tile_coord,
+											t.tile_type.with_liquid(Liquid {
+												sprite_override: true,
+												..t_liquid
+											}),
 											None,
 										);
 									}
 								}
-								if cont {
-									continue 'outer;
+								LiquidInteraction::Sink => {
+									nb.set_tile(maptile.tile_coord, t.tile_type, None);
+
+									nb.set_tile(below_coord, maptile.tile_type, None);
 								}
 							}
+							if cont {
+								continue 'outer;
+							}
 						}
 					}
+				} else if nb.reaches(below_coord) {
+					nb.wait_for(below_coord, maptile.tile_coord);
+				} else {
+					// out of reach for this task, nothing was changed yet
+					nb.defer(maptile.tile_coord, maptile.tile_type);
+					continue 'outer;
 				}
 			}
+		}
 
-			if fluidity < 10 && t.0 % (11 - fluidity as u64) != 0 {
-				continue;
+		if fluidity < 10 && !tick.is_multiple_of(11 - fluidity as u64) {
+			continue;
+		}
+
+		let left_coord = maptile.tile_coord.moved(&Vec2::NEG_X);
+		let right_coord = maptile.tile_coord.moved(&Vec2::X);
+
+		for coord in [left_coord, right_coord] {
+			match nb.map.get_tile(coord) {
+				Some(t)
+					if t.tile_type.is_liquid() && t.tile_type.id() != maptile.tile_type.id() =>
+				{
+					if let LiquidInteraction::React { this, other } =
+						maptile.tile_type.get_liquid_interaction_with(t.tile_type)
+					{
+						react(nb, maptile, this, t, other);
+						continue 'outer;
+					}
+				}
+				Some(_) => (),
+				None => nb.wait_for(coord, maptile.tile_coord),
 			}
+		}
 
-			let get_level = |coord| {
-				if let Some(t) = map.get_tile(coord) {
-					if discriminant(&t.tile_type) == discriminant(&maptile.tile_type) {
-						t.tile_type.liquid().level as i32 // existing liquid of same type
-					} else if !t.tile_type.is_solid() {
-						if t.tile_type.is_liquid() {
-							match maptile.tile_type.get_liquid_interaction_with(t.tile_type) {
-								LiquidInteraction::Vaporize => 0_i32,
-								LiquidInteraction::Vaporized => -1_i32,
-								LiquidInteraction::Float => -1_i32,
-								LiquidInteraction::Sink => -1_i32,
-							}
-						} else {
-							0_i32 // can flow
-						}
+		let get_level = |coord| {
+			if let Some(t) = nb.map.get_tile(coord) {
+				if t.tile_type.id() == maptile.tile_type.id() {
+					t.tile_type.liquid().level as i32 // existing liquid of same type
+				} else if !t.tile_type.is_solid() {
+					// other liquids don't mix with this one, and gases rise out of the way first
+					if t.tile_type.is_liquid() || t.tile_type.is_gas() {
+						-1_i32
 					} else {
-						-1_i32 // blocked by solid
+						0_i32 // can flow
 					}
 				} else {
-					-1_i32 // blocked by unloaded chunk
-				}
-			};
-
-			let left_coord = maptile.tile_coord.moved(&Vec2::NEG_X);
-			let right_coord = maptile.tile_coord.moved(&Vec2::X);
-			let mut left_level = get_level(left_coord);
-			let mut right_level = get_level(right_coord);
-			let left_level_initial = left_level;
-			let right_level_initial = right_level;
-			let mut this_level = maptile_liquid.level as i32;
-			let this_level_initial = maptile_liquid.level as i32;
-			let mut significant = false;
-
-			for lvl in [left_level_initial, right_level_initial] {
-				if lvl != -1 && (this_level - lvl).abs() > 1 {
-					significant = true;
-					break;
+					-1_i32 // blocked by solid
 				}
+			} else {
+				-1_i32 // blocked by unloaded chunk
 			}
+		};
 
-			let (mut flow_right, momentum) = if let Some(v) = maptile_liquid.flowing_right {
-				(
-					v,
-					if significant {
-						INITIAL_LIQUID_MOMENTUM
-					} else {
-						maptile_liquid.momentum - 1
-					},
-				)
-			} else {
-				(
-					rand_bool,
-					if significant {
-						INITIAL_LIQUID_MOMENTUM
-					} else {
-						1
-					},
-				)
-			};
+		let mut left_level = get_level(left_coord);
+		let mut right_level = get_level(right_coord);
+		let left_level_initial = left_level;
+		let right_level_initial = right_level;
+		let mut this_level = maptile_liquid.level as i32;
+		let this_level_initial = maptile_liquid.level as i32;
+		let mut significant = false;
+
+		for lvl in [left_level_initial, right_level_initial] {
+			if lvl != -1 && (this_level - lvl).abs() > 1 {
+				significant = true;
+				break;
+			}
+		}
 
-			let stagnant = momentum <= 1;
+		let (mut flow_right, momentum) = if let Some(v) = maptile_liquid.flowing_right {
+			(
+				v,
+				if significant {
+					INITIAL_LIQUID_MOMENTUM
+				} else {
+					maptile_liquid.momentum - 1
+				},
+			)
+		} else {
+			(
+				rand_bool,
+				if significant {
+					INITIAL_LIQUID_MOMENTUM
+				} else {
+					1
+				},
+			)
+		};
 
-			if stagnant {
-				commands.entity(tile_entity).remove::<FlowingTile>();
-			} else {
-				loop {
-					if this_level == 0 {
-						break;
-					}
+		let stagnant = momentum <= 1;
 
-					let left_blocked = left_level == -1 || left_level >= this_level;
-					let right_blocked = right_level == -1 || right_level >= this_level;
+		if stagnant {
+			stop_flowing(&mut nb.map, tile_coord);
+		} else {
+			loop {
+				if this_level == 0 {
+					break;
+				}
 
-					flow_right = if right_blocked {
-						if left_blocked {
-							break;
-						} else {
-							false
-						}
-					} else if left_blocked {
-						true
-					} else {
-						flow_right
-					};
+				let left_blocked = left_level == -1 || left_level >= this_level;
+				let right_blocked = right_level == -1 || right_level >= this_level;
 
-					if flow_right {
-						right_level += 1;
+				flow_right = if right_blocked {
+					if left_blocked {
+						break;
 					} else {
-						left_level += 1;
+						false
 					}
+				} else if left_blocked {
+					true
+				} else {
+					flow_right
+				};
 
-					this_level -= 1;
-					flow_right = !flow_right;
+				if flow_right {
+					right_level += 1;
+				} else {
+					left_level += 1;
 				}
+
+				this_level -= 1;
+				flow_right = !flow_right;
+			}
+
+			// the momentum is only stored when a level changes, so a tile that can't move
+			// would never become stagnant. it's woken up again when a neighbor changes
+			if this_level == this_level_initial
+				&& left_level == left_level_initial
+				&& right_level == right_level_initial
+			{
+				stop_flowing(&mut nb.map, tile_coord);
 			}
+		}
 
-			let mut set_liquid = |flow_right: bool, level: i32, level_initial, coord| match level {
-				_ if level > 0 => {
-					if level != level_initial {
-						let new_tile = maptile.tile_type.with_liquid(Liquid {
-							level: level as u8,
-							flowing_right: if stagnant { None } else { Some(!flow_right) },
-							momentum: if stagnant { 0 } else { momentum },
-							..maptile_liquid
-						});
-
-						set_tile(
-							&mut commands,
-							coord,
-							new_tile,
-							&sprites,
-							&mut map,
-							&mut ev_updatetile,
-							&mut ev_addlightsource,
-							&mut ev_updatelighting,
-							None,
-						);
+		let mut set_liquid = |flow_right: bool, level: i32, level_initial, coord| match level {
+			_ if level == level_initial => (),
+			_ if level > 0 => {
+				let new_tile = maptile.tile_type.with_liquid(Liquid {
+					level: level as u8,
+					flowing_right: if stagnant { None } else { Some(!flow_right) },
+					momentum: if stagnant { 0 } else { momentum },
+					..maptile_liquid
+				});
+
+				nb.set_tile(coord, new_tile, None);
+			}
+			0 => {
+				nb.set_tile(coord, TileType::EMPTY, None);
+			}
+			_ => (),
+		};
+
+		set_liquid(flow_right, left_level, left_level_initial, left_coord);
+		set_liquid(flow_right, right_level, right_level_initial, right_coord);
+
+		set_liquid(
+			flow_right,
+			this_level,
+			this_level_initial,
+			maptile.tile_coord,
+		);
+	}
+}
+
+/// Lets every tile with a lifetime decay once it's over, and moves the gases that didn't: up if
+/// there's room, otherwise diagonally up or to the side, which makes them spread out under
+/// ceilings. Gases only move into empty tiles. Falling tiles, liquids included, swap places with
+/// them, and liquids wait for them to move before flowing sideways.
+fn simulate_gases(
+	mut tick: EventReader<TickEvent>,
+	mut map: ResMut<Map>,
+	mut ev_updatetile: EventWriter<UpdateTileEvent>,
+	mut ev_addlightsource: EventWriter<AddLightSourceEvent>,
+	mut ev_updatelighting: EventWriter<LightingUpdateEvent>,
+	mut stats: ResMut<SimulationStats>,
+) {
+	for t in tick.read() {
+		let neighborhoods = simulate_phased(
+			&mut map,
+			|chunk| &mut chunk.decaying,
+			ScanOrder::RowsDownward,
+			|nb, tiles| rise(nb, tiles, t.0),
+		);
+
+		for nb in neighborhoods {
+			stats.simulated_tiles += nb.simulated_tiles;
+
+			nb.events.send(
+				&mut ev_updatetile,
+				&mut ev_addlightsource,
+				&mut ev_updatelighting,
+			);
+		}
+	}
+}
+
+fn rise(nb: &mut Neighborhood, tiles: Vec<(Coordinate, TileType)>, tick: u64) {
+	for (coord, tile_type) in tiles {
+		match nb.map.get_tile(coord) {
+			Some(t) if t.tile_type.id() == tile_type.id() => (),
+			_ => continue,
+		}
+
+		let noise = tick_noise(coord, tick);
+
+		if noise.is_multiple_of(tile_type.get_lifetime() as u32) {
+			nb.set_tile(coord, tile_type.decays_to(), None);
+			continue;
+		}
+
+		if !tile_type.is_gas() {
+			continue;
+		}
+
+		let side = if noise & (1 << 16) == 0 { -1.0 } else { 1.0 };
+
+		for direction in [
+			Vec2::Y,
+			Vec2::new(side, 1.0),
+			Vec2::new(-side, 1.0),
+			Vec2::new(side, 0.0),
+			Vec2::new(-side, 0.0),
+		] {
+			let target = coord.moved(&direction);
+
+			// unloaded chunks and chunks of other tasks don't show up in the map
+			if nb
+				.map
+				.get_tile(target)
+				.is_some_and(|t| t.tile_type.id() == TileType::EMPTY.id())
+			{
+				nb.set_tile(coord, TileType::EMPTY, None);
+				nb.set_tile(target, tile_type, None);
+				break;
+			}
+		}
+	}
+}
+
+/// Pseudo random number for a tile that changes every tick.
+fn tick_noise(coord: Coordinate, tick: u64) -> u32 {
+	let mut i = (coord.x_i32() as u32).wrapping_mul(0x9e37_79b9)
+		^ (coord.y_i32() as u32).wrapping_mul(0x85eb_ca6b)
+		^ (tick as u32).wrapping_mul(0xc2b2_ae35);
+
+	i ^= i >> 16;
+	i = i.wrapping_mul(0x7feb_352d);
+	i ^= i >> 15;
+	i = i.wrapping_mul(0x846c_a68b);
+	i ^ (i >> 16)
+}
+
+/// Turns the liquid `maptile` and the tile `other` it met into the products of their reaction.
+/// Liquids that react away are consumed.
+fn react(
+	nb: &mut Neighborhood,
+	maptile: MapTile,
+	product: Option<TileType>,
+	other: MapTile,
+	other_product: Option<TileType>,
+) {
+	for (t, product) in [(maptile, product), (other, other_product)] {
+		if let Some(product) = product {
+			if t.tile_type.is_liquid() {
+				nb.consumed.push(LiquidConsumedEvent {
+					coord: t.tile_coord,
+					tile_type: t.tile_type,
+				});
+			}
+
+			nb.set_tile(t.tile_coord, product, None);
+		}
+	}
+}
+
+/// Chunks are simulated in phases by their position modulo this. The chunks of a phase are
+/// three apart, so the chunks around them never overlap and can be handed to parallel tasks.
+const PHASE_SPACING: i32 = 3;
+
+/// A simulated chunk and the loaded chunks around it, moved out of the map while a physics
+/// task works on them, along with everything the task produced besides tile changes.
+struct Neighborhood {
+	center: (i32, i32),
+	/// Whether the task only owns the chunks around `center`, rather than the whole map.
+	bounded: bool,
+	map: Map,
+	events: TileEventBuffer,
+	consumed: Vec<LiquidConsumedEvent>,
+	/// Tiles that reached into an unloaded chunk, with the coordinate they reached.
+	waiting: Vec<(Coordinate, Coordinate)>,
+	/// Tiles that reached past the chunks of this task, simulated again after every task.
+	deferred: Vec<(Coordinate, TileType)>,
+	simulated_tiles: usize,
+}
+
+impl Neighborhood {
+	fn set_tile(&mut self, coord: Coordinate, tile_type: TileType, texture_index: Option<i32>) {
+		let _ = write_tile(
+			coord,
+			tile_type,
+			&mut self.map,
+			&mut self.events,
+			texture_index,
+		);
+	}
+
+	fn wait_for(&mut self, unloaded: Coordinate, tile: Coordinate) {
+		self.waiting.push((unloaded, tile));
+	}
+
+	fn defer(&mut self, coord: Coordinate, tile_type: TileType) {
+		self.deferred.push((coord, tile_type));
+	}
+
+	/// Whether `coord` is in one of the chunks this task owns, loaded or not. Tiles further
+	/// away may belong to another task of the same phase.
+	fn reaches(&self, coord: Coordinate) -> bool {
+		let chunk_coord = coord.as_chunk_coord();
+
+		!self.bounded
+			|| (chunk_coord.x_i32() - self.center.0).abs() <= 1
+				&& (chunk_coord.y_i32() - self.center.1).abs() <= 1
+	}
+}
+
+/// Runs `simulate` on the tiles in the active set picked by `set` of every awake simulated chunk,
+/// in `order`. The chunks of a phase are simulated in parallel, each with its neighbors taken
+/// out of the map, and the phases run one after another. Since no two tasks share a chunk, the
+/// result only depends on the phase order, which is fixed, and the neighborhoods are returned in
+/// the order their events should be sent in. Tiles deferred by their task are simulated last,
+/// one after another in a single task owning the whole map.
+fn simulate_phased(
+	map: &mut Map,
+	set: fn(&mut MapChunk) -> &mut ActiveTiles,
+	order: ScanOrder,
+	simulate: impl Fn(&mut Neighborhood, Vec<(Coordinate, TileType)>) + Sync,
+) -> Vec<Neighborhood> {
+	let mut centers = map
+		.iter_mut()
+		.filter_map(|(chunk_pos, chunk)| {
+			(chunk.tier.is_simulated() && !set(chunk).is_empty()).then_some(*chunk_pos)
+		})
+		.collect::<Vec<_>>();
+
+	match order {
+		ScanOrder::Rows => centers.sort_by_key(|p| (p.1, p.0)),
+		ScanOrder::RowsDownward => centers.sort_by_key(|p| (-p.1, p.0)),
+		ScanOrder::Columns { right_to_left } => {
+			centers.sort_by_key(|p| (if right_to_left { -p.0 } else { p.0 }, p.1))
+		}
+	}
+
+	let mut simulated = vec![];
+
+	for phase in 0..PHASE_SPACING * PHASE_SPACING {
+		let mut neighborhoods = centers
+			.iter()
+			.filter(|p| {
+				p.0.rem_euclid(PHASE_SPACING) == phase % PHASE_SPACING
+					&& p.1.rem_euclid(PHASE_SPACING) == phase / PHASE_SPACING
+			})
+			.map(|center| {
+				let mut chunks = Map::default();
+
+				for x in -1..=1 {
+					for y in -1..=1 {
+						let chunk_pos = (center.0 + x, center.1 + y);
+
+						if let Some(chunk) = map.remove(&chunk_pos) {
+							chunks.insert(chunk_pos, chunk);
+						}
 					}
 				}
-				0 => {
-					set_tile(
-						&mut commands,
-						coord,
-						TileType::Empty,
-						&sprites,
-						&mut map,
-						&mut ev_updatetile,
-						&mut ev_addlightsource,
-						&mut ev_updatelighting,
-						None,
-					);
-				}
-				_ => (),
-			};
 
-			set_liquid(flow_right, left_level, left_level_initial, left_coord);
-			set_liquid(flow_right, right_level, right_level_initial, right_coord);
+				Neighborhood {
+					center: *center,
+					bounded: true,
+					map: chunks,
+					events: TileEventBuffer::default(),
+					consumed: vec![],
+					waiting: vec![],
+					deferred: vec![],
+					simulated_tiles: 0,
+				}
+			})
+			.collect::<Vec<_>>();
+
+		ComputeTaskPool::get().scope(|scope| {
+			for nb in neighborhoods.iter_mut() {
+				let simulate = &simulate;
+
+				scope.spawn(async move {
+					let tiles = active_tiles(nb, set, order);
+					nb.simulated_tiles = tiles.len();
+					simulate(nb, tiles);
+				});
+			}
+		});
 
-			set_liquid(
-				flow_right,
-				this_level,
-				this_level_initial,
-				maptile.tile_coord,
-			);
+		for nb in neighborhoods.iter_mut() {
+			map.extend(nb.map.drain());
 		}
+
+		simulated.extend(neighborhoods);
+	}
+
+	let deferred = simulated
+		.iter_mut()
+		.flat_map(|nb| nb.deferred.drain(..))
+		.collect::<Vec<_>>();
+
+	if !deferred.is_empty() {
+		let mut nb = Neighborhood {
+			center: (0, 0),
+			bounded: false,
+			map: mem::take(map),
+			events: TileEventBuffer::default(),
+			consumed: vec![],
+			waiting: vec![],
+			deferred: vec![],
+			// already counted by the tasks deferring them
+			simulated_tiles: 0,
+		};
+
+		simulate(&mut nb, deferred);
+		*map = mem::take(&mut nb.map);
+		simulated.push(nb);
 	}
+
+	simulated
 }
 
+/// Every tile in the active set of the neighborhood's center chunk, with its tile type at the
+/// start of the task. Tiles in frozen chunks stay in their sets until the chunk is simulated.
+fn active_tiles(
+	nb: &mut Neighborhood,
+	set: fn(&mut MapChunk) -> &mut ActiveTiles,
+	order: ScanOrder,
+) -> Vec<(Coordinate, TileType)> {
+	let chunk_pos = nb.center;
+	let chunk = nb.map.get_mut(&chunk_pos).unwrap();
+
+	set(chunk)
+		.scan(order)
+		.into_iter()
+		.map(|key| {
+			(
+				Coordinate::Tile {
+					x: chunk_pos.0 * CHUNK_SIZE.0 as i32 + key.0 as i32,
+					y: chunk_pos.1 * CHUNK_SIZE.1 as i32 + key.1 as i32,
+				},
+				chunk.tile(key.0, key.1).tile_type,
+			)
+		})
+		.collect()
+}
+
+fn stop_flowing(map: &mut Map, coord: Coordinate) {
+	let chunklocal_coord = coord.as_chunklocal_coord();
+
+	if let Some(chunk) = map.get_chunk_mut(coord) {
+		chunk
+			.flowing
+			.remove((chunklocal_coord.x_u8(), chunklocal_coord.y_u8()));
+	}
+}
+
+/// Where a weighted tile falls to, if anywhere. Fails with the first tile checked that is in an
+/// unloaded chunk.
 fn get_fall_coord(
 	map: &Map,
 	current_position: Coordinate,
 	granularity: u8,
 	maptile: MapTile,
-) -> Result<Option<Coordinate>, ()> {
+) -> Result<Option<Coordinate>, Coordinate> {
 	let current_position = current_position.as_tile_coord();
 	let mut left_blocked = false;
 	let mut right_blocked = false;
@@ -642,17 +973,23 @@ fn get_fall_coord(
 		[1, -1]
 	};
 
+	// walks outwards along the tile's own row and the row below it, once per direction
+	let mut sides = directions.map(|m| map.line(current_position, IVec2::new(m, 0)));
+	let mut belows =
+		directions.map(|m| map.line(current_position.moved(&Vec2::NEG_Y), IVec2::new(m, 0)));
+
 	'outer: for x_abs in 0..=granularity {
-		for m in directions {
+		for (i, m) in directions.into_iter().enumerate() {
 			if (left_blocked && m == -1) || (right_blocked && m == 1) {
 				continue;
 			}
 
-			let x_i8 = x_abs as i8 * m as i8;
-			let x_f32 = x_i8 as f32;
+			let side_coord = current_position.moved(&Vec2::new((x_abs as i32 * m) as f32, 0.0));
+			let side = sides[i].next().flatten();
+			let below = belows[i].next().flatten();
 
-			if x_i8 != 0 {
-				match map.get_tile(current_position.moved(&Vec2::new(x_f32, 0.0))) {
+			if x_abs != 0 {
+				match side {
 					Some(t) => {
 						if maptile.tile_type.is_obstructed_by(t.tile_type) {
 							if m == -1 {
@@ -665,7 +1002,7 @@ fn get_fall_coord(
 							}
 						}
 					}
-					None => return Err(()),
+					None => return Err(side_coord),
 				}
 			}
 
@@ -673,17 +1010,15 @@ fn get_fall_coord(
 				continue;
 			}
 
-			let new_coord = current_position.moved(&Vec2::new(x_f32, -1.0));
-
-			match map.get_tile(new_coord) {
+			match below {
 				Some(t) => {
 					if !maptile.tile_type.is_obstructed_by(t.tile_type) {
-						return Ok(Some(new_coord));
+						return Ok(Some(t.tile_coord));
 					} else {
 						continue;
 					}
 				}
-				None => return Err(()),
+				None => return Err(side_coord.moved(&Vec2::NEG_Y)),
 			}
 		}
 
@@ -699,3 +1034,352 @@ pub struct UpdateTileEvent(pub Coordinate);
 
 #[derive(Event)]
 pub struct UpdateOutlineSpriteEvent(pub Coordinate);
+
+/// Sent whenever a reaction between liquids destroys `tile_type` at `coord`. These are the
+/// only changes to the total level of a liquid that the solver makes on its own.
+#[derive(Event)]
+pub struct LiquidConsumedEvent {
+	pub coord: Coordinate,
+	pub tile_type: TileType,
+}
+
+#[cfg(test)]
+mod tests {
+	use crate::{
+		testing::TestWorld,
+		tiletypes::{Liquid, TileType},
+	};
+
+	#[test]
+	fn sand_falls_onto_ground() {
+		let mut world = TestWorld::new(
+			"
+			..S..
+			.....
+			.....
+			",
+		);
+
+		world.tick(1);
+		world.assert_layout(
+			"
+			.....
+			..S..
+			.....
+			",
+		);
+
+		world.tick(1);
+		world.assert_layout(
+			"
+			.....
+			.....
+			..S..
+			",
+		);
+	}
+
+	#[test]
+	fn sand_piles_with_granularity_slope() {
+		let mut world = TestWorld::new(
+			"
+			...SSSS...
+			...SSSS...
+			..........
+			..........
+			..........
+			",
+		);
+
+		world.tick(20);
+		world.assert_layout(
+			"
+			..........
+			..........
+			..........
+			....SS....
+			..SSSSSS..
+			",
+		);
+	}
+
+	#[test]
+	fn water_levels_equalize() {
+		let mut world = TestWorld::new(
+			"
+			W.....
+			W.....
+			######
+			",
+		);
+		let total = world.total_level(TileType::named("water"));
+
+		world.tick(40);
+		world.assert_layout(
+			"
+			......
+			wwwwww
+			######
+			",
+		);
+
+		for x in 0..6 {
+			assert_eq!(world.tile(x, 1).liquid().level as u32, total / 6);
+		}
+
+		assert_eq!(world.total_level(TileType::named("water")), total);
+	}
+
+	#[test]
+	fn liquids_are_conserved() {
+		for layout in [
+			"
+			WWW.......
+			WWW.......
+			WWW...OO..
+			##########
+			",
+			"
+			.OO..WW...
+			.OO..WW...
+			..........
+			.##....##.
+			.##....##.
+			##########
+			",
+			"
+			..SSWW..ww
+			..SSWW..ww
+			.#......#.
+			.#.ww...#.
+			##########
+			",
+		] {
+			let mut world = TestWorld::new(layout);
+			let water = world.total_level(TileType::named("water"));
+			let oil = world.total_level(TileType::named("oil"));
+
+			world.tick(200);
+
+			assert!(world.liquid_drifts().is_empty(), "{}", world.layout());
+			assert_eq!(world.total_level(TileType::named("water")), water);
+			assert_eq!(world.total_level(TileType::named("oil")), oil);
+		}
+	}
+
+	#[test]
+	fn liquids_react_into_new_tiles() {
+		let mut world = TestWorld::new(
+			"
+			A#O#AW
+			W#A###
+			",
+		);
+
+		world.tick(2);
+
+		world.assert_layout(
+			"
+			X#F#X~
+			~#A###
+			",
+		);
+		assert!(world.liquid_drifts().is_empty());
+	}
+
+	#[test]
+	fn gases_rise_and_pool_under_ceilings() {
+		let mut world = TestWorld::new(
+			"
+			......
+			......
+			......
+			.~~~..
+			",
+		);
+
+		world.tick(20);
+
+		let layout = world.layout();
+		let (top, rest) = layout.split_once('\n').unwrap();
+
+		assert!(top.contains('~') && !rest.contains('~'), "{layout}");
+	}
+
+	#[test]
+	fn liquids_flowing_into_gases_keep_them() {
+		let mut world = TestWorld::new(
+			"
+			######
+			W~....
+			######
+			",
+		);
+		let water = world.total_level(TileType::named("water"));
+
+		// the steam can't rise under the ceiling, so it's pushed along as the water spreads
+		world.tick(10);
+
+		let layout = world.layout();
+
+		assert!(layout.matches('~').count() == 1, "{layout}");
+		assert!(world.tile(5, 1) == TileType::named("steam"), "{layout}");
+		assert_eq!(world.total_level(TileType::named("water")), water);
+	}
+
+	#[test]
+	fn burning_tiles_decay_into_smoke_and_vanish() {
+		let mut world = TestWorld::new(
+			"
+			...
+			.F.
+			",
+		);
+
+		world.tick(1);
+		assert!(world.stats().awake_chunks == 1);
+
+		world.tick(800);
+
+		world.assert_layout(
+			"
+			...
+			...
+			",
+		);
+		assert!(world.stats().awake_chunks == 0);
+	}
+
+	#[test]
+	fn frozen_chunks_are_not_simulated() {
+		let mut world = TestWorld::new(
+			"
+			S
+			.
+			",
+		);
+
+		// chunk (-1, 0) is loaded but frozen
+		world.set_tile(-5, 1, TileType::named("sand"));
+		world.set_tile(-5, 0, TileType::EMPTY);
+		world.tick(3);
+
+		world.assert_layout(
+			"
+			.
+			S
+			",
+		);
+		assert!(world.tile(-5, 1) == TileType::named("sand"));
+		assert!(world.tile(-5, 0) == TileType::EMPTY);
+	}
+
+	#[test]
+	fn tiles_wake_when_blocking_chunk_loads() {
+		let mut world = TestWorld::new(".");
+
+		// sand two tiles from the border, able to slide to the right into chunk (1, 0)
+		world.unload_chunk(1, 0);
+		world.set_tile(31, 5, TileType::EMPTY);
+		world.set_tile(30, 5, TileType::named("sand"));
+		world.tick(3);
+
+		assert!(world.tile(30, 5) == TileType::named("sand"));
+
+		// woken during the first tick, falls during the second
+		world.load_chunk(1, 0, TileType::EMPTY);
+		world.tick(2);
+
+		assert!(world.tile(30, 5) == TileType::EMPTY);
+		assert!(world.tile(32, 4) == TileType::named("sand"));
+	}
+
+	#[test]
+	fn settled_chunks_fall_asleep() {
+		let mut world = TestWorld::new(
+			"
+			.S.W.
+			.....
+			.....
+			",
+		);
+
+		world.tick(1);
+		assert!(world.stats().awake_chunks == 1);
+		assert!(world.stats().simulated_tiles > 0);
+
+		world.tick(20);
+		assert!(world.stats().awake_chunks == 0);
+		assert!(world.stats().simulated_tiles == 0);
+		assert!(world.stats().sleeping_chunks == 1);
+
+		// dropping a tile wakes the chunk up, but only the tiles around it are simulated
+		world.set_tile(1, 2, TileType::named("sand"));
+		world.tick(1);
+		assert!(world.stats().awake_chunks == 1);
+		assert!(world.stats().simulated_tiles <= 9);
+	}
+
+	#[test]
+	fn neighboring_chunks_simulate_deterministically() {
+		let run = || {
+			let mut world = TestWorld::new(".");
+
+			for x in -1..=1 {
+				for y in -1..=1 {
+					world.simulate_chunk(x, y);
+				}
+			}
+
+			// a cave spanning every simulated chunk, with sand and water falling across borders
+			world.fill((-20, -20), (51, 40), TileType::EMPTY);
+			world.fill((-12, 30), (44, 33), TileType::named("sand"));
+			world.fill((-16, 18), (48, 22), TileType::named("water"));
+
+			// the filled water itself shows up as a drift
+			let drifts = world.liquid_drifts().len();
+			world.tick(80);
+
+			assert!(world.liquid_drifts().len() == drifts);
+
+			let tiles = (-20..=51)
+				.flat_map(|x| (-20..=40).map(move |y| (x, y)))
+				.map(|(x, y)| world.tile(x, y))
+				.collect::<Vec<_>>();
+
+			let sand = tiles
+				.iter()
+				.filter(|t| **t == TileType::named("sand"))
+				.count();
+			assert!(sand == 57 * 4);
+			assert!(world.tile(0, -20) == TileType::named("sand"));
+
+			tiles
+		};
+
+		assert!(run() == run());
+	}
+
+	#[test]
+	fn liquids_reach_across_the_chunks_of_a_task() {
+		let mut world = TestWorld::new("#");
+		let water = |level| {
+			TileType::named("water").with_liquid(Liquid {
+				level,
+				..Default::default()
+			})
+		};
+
+		// a lake from chunk (-1, 0) to chunk (1, 0), with a dent at its left end that the tile
+		// on top of its right end drains into, more than a chunk away from its own
+		world.simulate_chunk(1, 0);
+		world.fill((-10, 1), (40, 5), TileType::EMPTY);
+		world.fill((-10, 1), (40, 1), TileType::named("water"));
+		world.set_tile(-10, 1, water(100));
+		world.set_tile(40, 2, water(100));
+		world.tick(1);
+
+		assert!(world.tile(40, 2) == TileType::EMPTY);
+		assert!(world.tile(-10, 1).liquid().level == 200);
+	}
+}
@@ -1,37 +1,16 @@
 use crate::{
-	grid::{xorshift_from_coord, Coordinate, Map, MapTile},
+	grid::{Coordinate, Map, MapTile},
 	light::{AddLightSourceEvent, LightingUpdateEvent},
-	playerphysics::Collider,
-	sprites::Sprites,
-	tilephysics::{FlowingTile, UpdateTileEvent},
+	tilephysics::UpdateTileEvent,
 	tiletypes::TileType,
 };
-use bevy::{
-	prelude::{Commands, Component, EventWriter, Quat, Transform, Vec2, Vec3},
-	sprite::SpriteBundle,
-};
-
-#[derive(Component)]
-pub struct Tile {
-	pub tile_type: TileType,
-	pub coord: Coordinate,
-}
-
-#[derive(Component)]
-pub struct WeightedTile {
-	pub granularity: u8,
-}
-
-#[derive(Component, Ord, Eq, PartialEq, PartialOrd)]
-pub struct FallingTile {
-	pub y: i32,
-}
+use bevy::prelude::{EventWriter, Vec2};
 
+/// Replaces the tile at `coord`. `texture_index` picks a sprite variant other than the one
+/// derived from the coordinate, which falling tiles use to animate.
 pub fn set_tile(
-	commands: &mut Commands,
 	coord: Coordinate,
 	tile_type: TileType,
-	sprites: &Sprites,
 	map: &mut Map,
 	update_tile_event: &mut EventWriter<UpdateTileEvent>,
 	event_add_lightsource: &mut EventWriter<AddLightSourceEvent>,
@@ -39,10 +18,8 @@ pub fn set_tile(
 	texture_index: Option<i32>,
 ) {
 	let _ = set_tile_result(
-		commands,
 		coord,
 		tile_type,
-		sprites,
 		map,
 		update_tile_event,
 		event_add_lightsource,
@@ -52,167 +29,148 @@ pub fn set_tile(
 }
 
 pub fn set_tile_result(
-	commands: &mut Commands,
 	coord: Coordinate,
 	tile_type: TileType,
-	sprites: &Sprites,
 	map: &mut Map,
 	update_tile_event: &mut EventWriter<UpdateTileEvent>,
 	event_add_lightsource: &mut EventWriter<AddLightSourceEvent>,
 	event_update_lighting: &mut EventWriter<LightingUpdateEvent>,
 	texture_index: Option<i32>,
 ) -> Result<MapTile, ()> {
-	let tile_coord = coord.as_tile_coord();
-	let chunklocal_coord = coord.as_chunklocal_coord();
-	let chunk_coord = coord.as_chunk_coord();
+	write_tile(
+		coord,
+		tile_type,
+		map,
+		&mut (
+			update_tile_event,
+			event_add_lightsource,
+			event_update_lighting,
+		),
+		texture_index,
+	)
+}
 
-	let maptile = if let Some(t) = map.get_tile(tile_coord) {
-		t
-	} else {
-		return Err(());
-	};
+/// Receives the events caused by changing a tile. Systems hand their event writers to
+/// `set_tile`, code running outside of a system collects the events in a `TileEventBuffer`.
+pub trait TileEvents {
+	fn update_tile(&mut self, ev: UpdateTileEvent);
+	fn add_lightsource(&mut self, ev: AddLightSourceEvent);
+	fn update_lighting(&mut self, ev: LightingUpdateEvent);
+}
 
-	commands
-		.entity(maptile.tile_entity)
-		.remove::<FallingTile>()
-		.remove::<FlowingTile>();
+impl TileEvents
+	for (
+		&mut EventWriter<'_, UpdateTileEvent>,
+		&mut EventWriter<'_, AddLightSourceEvent>,
+		&mut EventWriter<'_, LightingUpdateEvent>,
+	)
+{
+	fn update_tile(&mut self, ev: UpdateTileEvent) {
+		self.0.send(ev);
+	}
+
+	fn add_lightsource(&mut self, ev: AddLightSourceEvent) {
+		self.1.send(ev);
+	}
 
-	if !tile_type.is_visible() {
-		commands.entity(maptile.tile_entity).remove::<Tile>();
+	fn update_lighting(&mut self, ev: LightingUpdateEvent) {
+		self.2.send(ev);
 	}
+}
 
-	commands
-		.entity(maptile.sprite_entity)
-		.insert(create_tile_spritebundle(
-			tile_type,
-			tile_coord,
-			sprites,
-			texture_index,
-		));
+#[derive(Default)]
+pub struct TileEventBuffer {
+	update_tile: Vec<UpdateTileEvent>,
+	add_lightsource: Vec<AddLightSourceEvent>,
+	update_lighting: Vec<LightingUpdateEvent>,
+}
 
-	let mut cmds = commands.entity(maptile.tile_entity);
+impl TileEvents for TileEventBuffer {
+	fn update_tile(&mut self, ev: UpdateTileEvent) {
+		self.update_tile.push(ev);
+	}
 
-	cmds.insert(Tile {
-		tile_type,
-		coord: tile_coord,
-	});
+	fn add_lightsource(&mut self, ev: AddLightSourceEvent) {
+		self.add_lightsource.push(ev);
+	}
 
-	if tile_type.is_solid() {
-		cmds.insert(Collider);
-	} else {
-		cmds.remove::<Collider>();
+	fn update_lighting(&mut self, ev: LightingUpdateEvent) {
+		self.update_lighting.push(ev);
 	}
+}
 
-	if tile_type.is_weighted() {
-		cmds.insert(WeightedTile {
-			granularity: tile_type.get_granularity(),
-		});
-	} else {
-		cmds.remove::<WeightedTile>();
+impl TileEventBuffer {
+	/// Sends the buffered events in the order they were collected in.
+	pub fn send(
+		self,
+		update_tile_event: &mut EventWriter<UpdateTileEvent>,
+		event_add_lightsource: &mut EventWriter<AddLightSourceEvent>,
+		event_update_lighting: &mut EventWriter<LightingUpdateEvent>,
+	) {
+		update_tile_event.send_batch(self.update_tile);
+		event_add_lightsource.send_batch(self.add_lightsource);
+		event_update_lighting.send_batch(self.update_lighting);
 	}
+}
+
+/// `set_tile_result` with the events going to `events`.
+pub fn write_tile(
+	coord: Coordinate,
+	tile_type: TileType,
+	map: &mut Map,
+	events: &mut impl TileEvents,
+	texture_index: Option<i32>,
+) -> Result<MapTile, ()> {
+	let tile_coord = coord.as_tile_coord();
+	let chunklocal_coord = coord.as_chunklocal_coord();
+	let chunklocal_key = (chunklocal_coord.x_u8(), chunklocal_coord.y_u8());
+
+	let maptile = if let Some(t) = map.get_tile(tile_coord) {
+		t
+	} else {
+		return Err(());
+	};
 
 	let new_maptile = MapTile {
 		tile_type,
 		outline_id: 50,
+		texture_index,
 		..maptile
 	};
 
 	for x in -1..=1 {
 		for y in -1..=1 {
-			update_tile_event.send(UpdateTileEvent(
+			events.update_tile(UpdateTileEvent(
 				tile_coord.moved(&Vec2::new(x as f32, y as f32)),
 			));
 		}
 	}
 
-	let maptile_mut = map
-		.get_mut(&(chunk_coord.x_i32(), chunk_coord.y_i32()))
-		.unwrap()
-		.tiles
-		.get_mut(&(chunklocal_coord.x_u8(), chunklocal_coord.y_u8()));
+	let chunk = map.get_chunk_mut(tile_coord).unwrap();
 
-	if let Some(v) = maptile_mut {
-		if v.tile_type.is_emitter() {
-			// todo remove lightsource
-		}
+	chunk.modified = true;
+	chunk.falling.remove(chunklocal_key);
+	chunk.flowing.remove(chunklocal_key);
+	chunk.decaying.remove(chunklocal_key);
+	chunk.redraw.insert(chunklocal_key);
 
-		if new_maptile.tile_type.is_emitter() {
-			event_add_lightsource.send(AddLightSourceEvent(new_maptile));
-		}
+	let v = chunk.tile_mut(chunklocal_key.0, chunklocal_key.1);
 
-		if v.tile_type.is_opaque() != new_maptile.tile_type.is_opaque() {
-			event_update_lighting.send(LightingUpdateEvent(v.tile_coord));
-		}
+	// liquids that only changed their level keep their light source
+	let emitter_changed = v.tile_type.get_emitter() != new_maptile.tile_type.get_emitter();
 
-		*v = new_maptile;
+	if emitter_changed && new_maptile.tile_type.is_emitter() {
+		events.add_lightsource(AddLightSourceEvent(new_maptile));
 	}
 
-	Ok(new_maptile)
-}
+	// the light source of a replaced emitter is removed by the lighting update
+	if (emitter_changed && v.tile_type.is_emitter())
+		|| v.tile_type.get_opacity() != new_maptile.tile_type.get_opacity()
+	{
+		events.update_lighting(LightingUpdateEvent(v.tile_coord));
+	}
 
-pub fn create_tile_spritebundle(
-	tile_type: TileType,
-	coord: Coordinate,
-	sprites: &Sprites,
-	texture_index: Option<i32>,
-) -> SpriteBundle {
-	let tile_coord = coord.as_tile_coord();
+	*v = new_maptile;
 
-	if !tile_type.is_visible() {
-		SpriteBundle {
-			texture: sprites.tile_outlines.get(39).unwrap().clone(), // use no outline sprite for tile sprite
-			transform: Transform {
-				translation: Vec3::ZERO,
-				..Default::default()
-			},
-			..Default::default()
-		}
-	} else if !tile_type.is_liquid() {
-		let texture_handle = sprites.tiles.get(&tile_type.to_string()).unwrap();
-
-		let mut i = xorshift_from_coord(tile_coord);
-
-		let (rot, scale) = if tile_type.morph_sprite() {
-			(
-				(i % 4) as f32 * std::f32::consts::FRAC_PI_2,
-				match (i / 10) % 3 {
-					1 => (1.0, -1.0), // flip vertical
-					2 => (-1.0, 1.0), // flip horizontal
-					_ => (1.0, 1.0),  // no flip
-				},
-			)
-		} else {
-			(0.0, (1.0, 1.0))
-		};
-
-		i = texture_index.unwrap_or(i).abs() % texture_handle.len() as i32;
-
-		SpriteBundle {
-			texture: texture_handle.get(i as usize).unwrap().clone(),
-			transform: Transform {
-				rotation: Quat::from_rotation_z(rot),
-				scale: Vec3::new(scale.0, scale.1, 1.0),
-				translation: Vec3::ZERO,
-			},
-			..Default::default()
-		}
-	} else {
-		let texture_handle = sprites.tiles.get(&tile_type.to_string()).unwrap();
-
-		let i = if tile_type.liquid().sprite_override {
-			texture_handle.len() - 1
-		} else {
-			((texture_handle.len() as f32 - 1.0)
-				* (tile_type.liquid().level as f32 / u8::MAX as f32)) as usize
-		};
-
-		let t = texture_handle
-			.get(i)
-			.unwrap_or_else(|| panic!("Missing liquid tile texture: {tile_type} index {i}"));
-
-		SpriteBundle {
-			texture: t.clone(),
-			..Default::default()
-		}
-	}
+	Ok(new_maptile)
 }
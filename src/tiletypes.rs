@@ -1,41 +1,91 @@
 use crate::light::Emitter;
-use strum_macros::{Display, EnumIter};
-
-#[derive(Copy, Clone, PartialEq, EnumIter, Display)]
-#[strum(serialize_all = "snake_case")]
-pub enum TileType {
-	Empty,
-	Gravel,
-	Moss,
-	Dirt,
-	Sand,
-	Water(Liquid),
-	Magma(Liquid),
-	Oil(Liquid),
-	Lantern(Emitter),
+use bevy::{prelude::Color, utils::HashMap};
+use serde::Deserialize;
+use std::{
+	env,
+	fmt::{self, Display, Formatter},
+	fs,
+	path::{Path, PathBuf},
+	sync::OnceLock,
+};
+
+/// Where the tile definitions are loaded from, relative to the asset folder.
+const TILE_DEFINITIONS_PATH: &str = "tiles.ron";
+
+static REGISTRY: OnceLock<TileRegistry> = OnceLock::new();
+static KNOWN_TILES: OnceLock<KnownTiles> = OnceLock::new();
+
+/// `path` in the asset folder, found the way `AssetPlugin` finds it: next to `BEVY_ASSET_ROOT`,
+/// the crate the game is run from with cargo, or the executable.
+pub fn asset_path(path: &str) -> PathBuf {
+	let root = match env::var_os("BEVY_ASSET_ROOT").or_else(|| env::var_os("CARGO_MANIFEST_DIR")) {
+		Some(v) => PathBuf::from(v),
+		None => env::current_exe()
+			.ok()
+			.and_then(|p| p.parent().map(Path::to_path_buf))
+			.unwrap_or_default(),
+	};
+
+	root.join("assets").join(path)
+}
+
+/// A tile in the map: which definition it uses, plus the state that can differ between tiles of
+/// the same definition.
+#[derive(Copy, Clone, PartialEq)]
+pub struct TileType {
+	id: TileId,
+	state: TileState,
+}
+
+/// Index of a tile definition, also used as its tag in chunk files.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+pub struct TileId(pub u8);
+
+#[derive(Copy, Clone, PartialEq)]
+enum TileState {
+	None,
+	Liquid(Liquid),
+	Emitter(Emitter),
 }
 
 impl TileType {
+	pub const EMPTY: TileType = TileType {
+		id: TileId(0),
+		state: TileState::None,
+	};
+
+	/// The tile defined as `name`, with the default liquid or light of its definition.
+	/// Panics if no such tile is defined. The game itself uses `known_tiles` instead.
+	#[cfg(test)]
+	pub fn named(name: &str) -> TileType {
+		match registry().by_name.get(name) {
+			Some(id) => registry().make(*id),
+			None => panic!("No tile named \"{name}\" is defined in {TILE_DEFINITIONS_PATH}"),
+		}
+	}
+
+	pub fn id(&self) -> TileId {
+		self.id
+	}
+
+	pub fn name(&self) -> &'static str {
+		&self.definition().name
+	}
+
+	fn definition(&self) -> &'static TileDefinition {
+		registry().get(self.id)
+	}
+
 	pub fn morph_sprite(&self) -> bool {
-		true
+		self.definition().morph_sprite
 	}
 
 	pub fn is_weighted(&self) -> bool {
-		if self.is_liquid() {
-			return true;
-		}
-
-		matches!(self, TileType::Gravel | TileType::Sand)
+		self.is_liquid() || self.definition().weighted
 	}
 
 	pub fn get_matter_state(&self) -> Option<MatterState> {
-		if let TileType::Empty = self {
-			None
-		} else if self.get_liquid().is_ok() {
-			Some(MatterState::Liquid)
-		} else {
-			Some(MatterState::Solid)
-		}
+		self.definition().state
 	}
 
 	pub fn get_granularity(&self) -> u8 {
@@ -43,66 +93,65 @@ impl TileType {
 			return (self.get_fluidity() as f32
 				* ((u8::MAX as f32 - liquid.level as f32) / u8::MAX as f32)) as u8;
 		}
-		match self {
-			TileType::Gravel => 1,
-			TileType::Sand => 2,
-			_ => 0,
-		}
+
+		self.definition().granularity
 	}
 
 	pub fn get_liquid(&self) -> Result<Liquid, ()> {
-		match self {
-			TileType::Water(l) => Ok(*l),
-			TileType::Magma(l) => Ok(*l),
-			TileType::Oil(l) => Ok(*l),
+		match self.state {
+			TileState::Liquid(l) => Ok(l),
 			_ => Err(()),
 		}
 	}
 
 	pub fn with_liquid(&self, l: Liquid) -> TileType {
-		match self {
-			TileType::Water(_) => TileType::Water(l),
-			TileType::Magma(_) => TileType::Magma(l),
-			TileType::Oil(_) => TileType::Oil(l),
+		match self.state {
+			TileState::Liquid(_) => TileType {
+				state: TileState::Liquid(l),
+				..*self
+			},
 			_ => panic!("with_liquid() not implemented for passed tiletype: {self}"),
 		}
 	}
 
 	pub fn get_fluidity(&self) -> u8 {
-		match self {
-			TileType::Water(_) => 10,
-			TileType::Magma(_) => 1,
-			TileType::Oil(_) => 5,
-			_ => panic!("get_fluidity() not implemented for passed tiletype: {self}",),
+		if !self.is_liquid() {
+			panic!("get_fluidity() not implemented for passed tiletype: {self}");
 		}
+
+		self.definition().fluidity
 	}
 
+	/// What happens when this liquid meets `other`. Liquids that don't list each other in their
+	/// interactions don't mix, and this one stays on top.
 	pub fn get_liquid_interaction_with(&self, other: TileType) -> LiquidInteraction {
-		match self {
-			TileType::Water(_) => match other {
-				TileType::Magma(_) => return LiquidInteraction::Vaporized,
-				TileType::Oil(_) => return LiquidInteraction::Sink,
-				_ => (),
-			},
-			TileType::Magma(_) => match other {
-				TileType::Water(_) => return LiquidInteraction::Vaporize,
-				TileType::Oil(_) => return LiquidInteraction::Vaporize,
-				_ => (),
-			},
-			TileType::Oil(_) => match other {
-				TileType::Water(_) => return LiquidInteraction::Float,
-				TileType::Magma(_) => return LiquidInteraction::Vaporized,
-				_ => (),
-			},
-			_ => (),
+		match registry().interactions.get(&(self.id, other.id)) {
+			Some(v) => *v,
+			None => LiquidInteraction::Float,
 		}
-		panic!("get_liquid_interaction_with() not implemented: {self} -> {other}");
 	}
 
+	/// The light of the tile. Liquids keep their state for the liquid, so their light always
+	/// comes from their definition.
 	pub fn get_emitter(&self) -> Result<Emitter, ()> {
-		match self {
-			TileType::Lantern(e) => Ok(*e),
-			_ => Err(()),
+		match self.state {
+			TileState::Emitter(e) => Ok(e),
+			TileState::Liquid(_) => match &self.definition().light {
+				Some(light) => Ok(light.emitter()),
+				None => Err(()),
+			},
+			TileState::None => Err(()),
+		}
+	}
+
+	/// The tile with its light replaced, for emitters loaded from a save.
+	pub fn with_emitter(&self, e: Emitter) -> TileType {
+		match self.state {
+			TileState::Emitter(_) => TileType {
+				state: TileState::Emitter(e),
+				..*self
+			},
+			_ => panic!("with_emitter() not implemented for passed tiletype: {self}"),
 		}
 	}
 
@@ -119,7 +168,7 @@ impl TileType {
 	}
 
 	pub fn is_visible(&self) -> bool {
-		!matches!(self, TileType::Empty)
+		self.definition().sprite.is_some()
 	}
 
 	pub fn is_solid(&self) -> bool {
@@ -127,11 +176,28 @@ impl TileType {
 	}
 
 	pub fn is_liquid(&self) -> bool {
-		matches!(self.get_matter_state(), Some(MatterState::Liquid))
+		matches!(self.state, TileState::Liquid(_))
+	}
+
+	pub fn is_gas(&self) -> bool {
+		matches!(self.get_matter_state(), Some(MatterState::Gas))
+	}
+
+	/// Average number of ticks before the tile decays, 0 if it never does.
+	pub fn get_lifetime(&self) -> u16 {
+		self.definition().lifetime
+	}
+
+	/// The tile this one turns into when its lifetime is over.
+	pub fn decays_to(&self) -> TileType {
+		match registry().decays_to.get(&self.id) {
+			Some(v) => *v,
+			None => TileType::EMPTY,
+		}
 	}
 
-	pub fn is_opaque(&self) -> bool {
-		!matches!(self, TileType::Water(_) | TileType::Empty)
+	pub fn get_opacity(&self) -> u8 {
+		self.definition().opacity
 	}
 
 	pub fn is_obstructed_by(&self, other: TileType) -> bool {
@@ -145,9 +211,18 @@ impl TileType {
 	}
 }
 
+impl Display for TileType {
+	fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+		write!(f, "{}", self.name())
+	}
+}
+
+#[derive(Copy, Clone, PartialEq, Deserialize)]
 pub enum MatterState {
 	Solid,
 	Liquid,
+	/// Rises instead of falling and spreads out under whatever stops it.
+	Gas,
 }
 
 #[derive(Copy, Clone, PartialEq)]
@@ -169,9 +244,436 @@ impl Default for Liquid {
 	}
 }
 
+/// What happens when a liquid meets another liquid, seen from the first one.
+#[derive(Copy, Clone)]
 pub enum LiquidInteraction {
-	Vaporize,
-	Vaporized,
+	/// Stays on top of the other liquid.
 	Float,
+	/// Swaps places with the other liquid.
 	Sink,
+	/// Turns this tile and the other one into new tiles. `None` leaves a tile as it is.
+	React {
+		this: Option<TileType>,
+		other: Option<TileType>,
+	},
+}
+
+/// A `LiquidInteraction` in the definitions file, naming the tiles a reaction produces.
+#[derive(Deserialize)]
+pub enum InteractionDefinition {
+	Float,
+	Sink,
+	React {
+		#[serde(default)]
+		this: Option<String>,
+		#[serde(default)]
+		other: Option<String>,
+	},
+}
+
+/// One entry of the tile definitions file.
+#[derive(Deserialize)]
+pub struct TileDefinition {
+	pub id: u8,
+	pub name: String,
+	/// Tiles without a matter state, like empty space, are passed through by everything.
+	#[serde(default)]
+	pub state: Option<MatterState>,
+	/// Whether solid tiles fall. Liquids always do.
+	#[serde(default)]
+	pub weighted: bool,
+	/// How many tiles to the side a weighted solid can slide off of when it lands.
+	#[serde(default)]
+	pub granularity: u8,
+	/// How far and how often a liquid flows, from 1 to 10.
+	#[serde(default)]
+	pub fluidity: u8,
+	/// How much of the light passing through the tile it stops, from 0 for none to 255 for all.
+	#[serde(default = "opacity_default")]
+	pub opacity: u8,
+	/// Average number of ticks before the tile turns into `decays_to`. Gases need one.
+	#[serde(default)]
+	pub lifetime: u16,
+	/// What the tile decays into, empty if omitted.
+	#[serde(default)]
+	pub decays_to: Option<String>,
+	/// Light emitted by every tile of this type.
+	#[serde(default)]
+	pub light: Option<LightDefinition>,
+	/// Folder in `assets/tiles` holding the sprite variants. Tiles without one aren't drawn.
+	#[serde(default)]
+	pub sprite: Option<String>,
+	/// Whether sprites are turned and flipped depending on the coordinate.
+	#[serde(default = "morph_sprite_default")]
+	pub morph_sprite: bool,
+	/// Digit key that places this tile with the devtools.
+	#[serde(default)]
+	pub hotkey: Option<u8>,
+	/// What happens when this liquid meets another liquid, by the other liquid's name.
+	#[serde(default)]
+	pub interactions: HashMap<String, InteractionDefinition>,
+}
+
+fn opacity_default() -> u8 {
+	u8::MAX
+}
+
+fn morph_sprite_default() -> bool {
+	true
+}
+
+#[derive(Deserialize)]
+pub struct LightDefinition {
+	pub radius: u8,
+	/// sRGB color of the light, white if omitted.
+	#[serde(default)]
+	pub color: Option<(u8, u8, u8)>,
+}
+
+impl LightDefinition {
+	fn emitter(&self) -> Emitter {
+		Emitter {
+			radius: self.radius,
+			color: self.color.map(|(r, g, b)| Color::srgb_u8(r, g, b)),
+		}
+	}
+}
+
+/// Every tile definition, by id. Loaded once and then shared by everything that asks a
+/// `TileType` about its properties.
+pub struct TileRegistry {
+	definitions: Vec<Option<TileDefinition>>,
+	by_name: HashMap<String, TileId>,
+	interactions: HashMap<(TileId, TileId), LiquidInteraction>,
+	decays_to: HashMap<TileId, TileType>,
+}
+
+/// The loaded tile definitions. Loads them on first use if `TileRegistry::init` wasn't called.
+pub fn registry() -> &'static TileRegistry {
+	REGISTRY.get_or_init(
+		|| match TileRegistry::load(&asset_path(TILE_DEFINITIONS_PATH)) {
+			Ok(v) => v,
+			Err(e) => panic!("{e}"),
+		},
+	)
+}
+
+/// The tiles the game places by name, like the terrain of the biomes.
+pub fn known_tiles() -> &'static KnownTiles {
+	KNOWN_TILES.get_or_init(|| match KnownTiles::resolve(registry()) {
+		Ok(v) => v,
+		Err(e) => panic!("{e}"),
+	})
+}
+
+/// Tiles that world generation places, looked up once instead of by name for every tile. Their
+/// definitions have to exist for the tile definitions to load.
+pub struct KnownTiles {
+	pub sand: TileType,
+	pub dirt: TileType,
+	pub gravel: TileType,
+	pub moss: TileType,
+	pub water: TileType,
+	pub magma: TileType,
+	pub oil: TileType,
+	pub lantern: TileType,
+}
+
+impl KnownTiles {
+	fn resolve(registry: &TileRegistry) -> Result<Self, String> {
+		let tile = |name: &str, liquid: bool| match registry.by_name.get(name) {
+			Some(id) if (registry.get(*id).state == Some(MatterState::Liquid)) == liquid => {
+				Ok(registry.make(*id))
+			}
+			Some(_) if liquid => Err(format!("Tile \"{name}\" has to be a liquid")),
+			Some(_) => Err(format!("Tile \"{name}\" can't be a liquid")),
+			None => Err(format!(
+				"Tile \"{name}\" is used by the game but isn't defined"
+			)),
+		};
+
+		Ok(KnownTiles {
+			sand: tile("sand", false)?,
+			dirt: tile("dirt", false)?,
+			gravel: tile("gravel", false)?,
+			moss: tile("moss", false)?,
+			water: tile("water", true)?,
+			magma: tile("magma", true)?,
+			oil: tile("oil", true)?,
+			lantern: tile("lantern", false)?,
+		})
+	}
+}
+
+impl TileRegistry {
+	/// Loads the tile definitions, so a broken definitions file is reported before
+	/// anything uses them.
+	pub fn init() -> Result<(), String> {
+		if REGISTRY.get().is_none() {
+			let _ = REGISTRY.set(TileRegistry::load(&asset_path(TILE_DEFINITIONS_PATH))?);
+		}
+
+		Ok(())
+	}
+
+	pub fn load(path: &Path) -> Result<Self, String> {
+		let contents = fs::read_to_string(path)
+			.map_err(|e| format!("Failed to read {}: {e}", path.display()))?;
+
+		Self::parse(&contents).map_err(|e| format!("Failed to load {}: {e}", path.display()))
+	}
+
+	pub fn parse(contents: &str) -> Result<Self, String> {
+		let definitions: Vec<TileDefinition> =
+			ron::from_str(contents).map_err(|e| e.to_string())?;
+
+		let mut registry = TileRegistry {
+			definitions: vec![],
+			by_name: HashMap::new(),
+			interactions: HashMap::new(),
+			decays_to: HashMap::new(),
+		};
+
+		for definition in definitions {
+			let id = definition.id as usize;
+
+			if registry.definitions.len() <= id {
+				registry.definitions.resize_with(id + 1, || None);
+			}
+
+			if registry.definitions[id].is_some() {
+				return Err(format!("Tile id {id} is used more than once"));
+			}
+
+			if registry
+				.by_name
+				.insert(definition.name.clone(), TileId(definition.id))
+				.is_some()
+			{
+				return Err(format!(
+					"Tile \"{}\" is defined more than once",
+					definition.name
+				));
+			}
+
+			let liquid = definition.state == Some(MatterState::Liquid);
+
+			if liquid && !(1..=10).contains(&definition.fluidity) {
+				return Err(format!(
+					"Liquid \"{}\" needs a fluidity from 1 to 10",
+					definition.name
+				));
+			}
+
+			if liquid && definition.lifetime != 0 {
+				return Err(format!("Liquid \"{}\" can't decay", definition.name));
+			}
+
+			if definition.state == Some(MatterState::Gas)
+				&& (definition.lifetime == 0 || definition.weighted || definition.light.is_some())
+			{
+				return Err(format!(
+					"Gas \"{}\" needs a lifetime, and can't be weighted or emit light",
+					definition.name
+				));
+			}
+
+			registry.definitions[id] = Some(definition);
+		}
+
+		match registry.definitions.first() {
+			Some(Some(d)) if d.state.is_none() && d.sprite.is_none() => (),
+			_ => return Err("Tile 0 has to be an invisible tile without a matter state".into()),
+		}
+
+		let mut interactions = HashMap::new();
+		let mut decays_to = HashMap::new();
+
+		for definition in registry.definitions.iter().flatten() {
+			if definition.lifetime != 0 {
+				if let Some(product) =
+					registry.product(&definition.name, definition.decays_to.as_deref())?
+				{
+					decays_to.insert(TileId(definition.id), product);
+				}
+			}
+
+			if definition.state != Some(MatterState::Liquid) && !definition.interactions.is_empty()
+			{
+				return Err(format!(
+					"\"{}\" isn't a liquid, so it can't have interactions",
+					definition.name
+				));
+			}
+
+			for (name, interaction) in definition.interactions.iter() {
+				let other = match registry.by_name.get(name) {
+					Some(v) if registry.get(*v).state == Some(MatterState::Liquid) => *v,
+					_ => {
+						return Err(format!(
+							"Liquid \"{}\" has an interaction with \"{name}\", which isn't a liquid",
+							definition.name
+						))
+					}
+				};
+
+				let interaction = match interaction {
+					InteractionDefinition::Float => LiquidInteraction::Float,
+					InteractionDefinition::Sink => LiquidInteraction::Sink,
+					InteractionDefinition::React { this, other } => LiquidInteraction::React {
+						this: registry.product(&definition.name, this.as_deref())?,
+						other: registry.product(&definition.name, other.as_deref())?,
+					},
+				};
+
+				interactions.insert((TileId(definition.id), other), interaction);
+			}
+		}
+
+		registry.interactions = interactions;
+		registry.decays_to = decays_to;
+
+		KnownTiles::resolve(&registry)?;
+
+		Ok(registry)
+	}
+
+	fn get(&self, id: TileId) -> &TileDefinition {
+		match self.definitions.get(id.0 as usize) {
+			Some(Some(v)) => v,
+			_ => panic!("Tile id {} isn't defined", id.0),
+		}
+	}
+
+	/// The tile `source` turns a tile into by reacting or decaying, which can't be a liquid: the
+	/// level of a liquid made out of nothing couldn't be accounted for.
+	fn product(&self, source: &str, name: Option<&str>) -> Result<Option<TileType>, String> {
+		let name = match name {
+			Some(v) => v,
+			None => return Ok(None),
+		};
+
+		match self.by_name.get(name) {
+			Some(id) if self.get(*id).state == Some(MatterState::Liquid) => Err(format!(
+				"\"{source}\" can't turn a tile into the liquid \"{name}\""
+			)),
+			Some(id) => Ok(Some(self.make(*id))),
+			None => Err(format!(
+				"\"{source}\" turns a tile into unknown tile \"{name}\""
+			)),
+		}
+	}
+
+	/// A tile of the definition `id` with its default liquid or light.
+	fn make(&self, id: TileId) -> TileType {
+		let definition = self.get(id);
+
+		let state = if let Some(MatterState::Liquid) = definition.state {
+			TileState::Liquid(Liquid::default())
+		} else if let Some(light) = &definition.light {
+			TileState::Emitter(light.emitter())
+		} else {
+			TileState::None
+		};
+
+		TileType { id, state }
+	}
+
+	/// The tile with `id`, if it is defined.
+	pub fn tile(&self, id: TileId) -> Option<TileType> {
+		match self.definitions.get(id.0 as usize) {
+			Some(Some(_)) => Some(self.make(id)),
+			_ => None,
+		}
+	}
+
+	/// Every defined tile in id order, with default state.
+	pub fn iter(&self) -> impl Iterator<Item = TileType> + '_ {
+		self.definitions
+			.iter()
+			.flatten()
+			.map(|d| self.make(TileId(d.id)))
+	}
+
+	/// The sprite folder of a tile, see `TileDefinition::sprite`.
+	pub fn sprite(&self, tile_type: TileType) -> Option<&str> {
+		self.get(tile_type.id).sprite.as_deref()
+	}
+
+	/// The tile placed by a devtools digit key.
+	pub fn by_hotkey(&self, digit: u8) -> Option<TileType> {
+		self.definitions
+			.iter()
+			.flatten()
+			.find(|d| d.hotkey == Some(digit))
+			.map(|d| self.make(TileId(d.id)))
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use super::{asset_path, known_tiles, registry, TileRegistry, TileType, TILE_DEFINITIONS_PATH};
+
+	#[test]
+	fn shipped_definitions_load() {
+		assert!(TileRegistry::load(&asset_path(TILE_DEFINITIONS_PATH)).is_ok());
+
+		for tile_type in registry().iter() {
+			assert!(TileType::named(tile_type.name()) == tile_type);
+		}
+
+		assert!(registry().by_hotkey(0) == Some(TileType::EMPTY));
+		assert!(TileType::named("lantern").is_emitter());
+		assert!(TileType::named("magma").is_emitter());
+		assert_eq!(TileType::named("water").get_fluidity(), 10);
+		assert!(known_tiles().lantern == TileType::named("lantern"));
+	}
+
+	#[test]
+	fn invalid_definitions_are_rejected() {
+		// the tiles the game places itself, at ids out of the way of the tested ones
+		let known = "(id: 100, name: \"sand\"), (id: 101, name: \"dirt\"), \
+			(id: 102, name: \"gravel\"), (id: 103, name: \"moss\"), \
+			(id: 104, name: \"water\", state: Liquid, fluidity: 10), \
+			(id: 105, name: \"magma\", state: Liquid, fluidity: 1), \
+			(id: 106, name: \"oil\", state: Liquid, fluidity: 5), \
+			(id: 107, name: \"lantern\")";
+		let parse = |definitions: &str| {
+			TileRegistry::parse(&format!(
+				"#![enable(implicit_some)] [(id: 0, name: \"empty\"), {known}, {definitions}]"
+			))
+		};
+
+		// the tiles the game places missing, or magma not being a liquid
+		assert!(TileRegistry::parse("[(id: 0, name: \"empty\")]").is_err());
+		assert!(TileRegistry::parse(&format!(
+			"[(id: 0, name: \"empty\"), {}]",
+			known.replace("Liquid, fluidity: 1", "Solid")
+		))
+		.is_err());
+		assert!(parse("").is_ok());
+		assert!(parse("(id: 0, name: \"void\")").is_err());
+		assert!(parse("(id: 1, name: \"empty\")").is_err());
+		assert!(parse("(id: 1, name: \"tar\", state: Liquid)").is_err());
+		assert!(
+			parse("(id: 1, name: \"tar\", state: Liquid, fluidity: 2, light: (radius: 5))").is_ok()
+		);
+		let liquids = |interaction: &str| {
+			parse(&format!(
+				"(id: 1, name: \"tar\", state: Liquid, fluidity: 2, interactions: {{\"brine\": {interaction}}}), \
+				(id: 2, name: \"brine\", state: Liquid, fluidity: 8), \
+				(id: 3, name: \"pitch\", state: Solid)"
+			))
+		};
+
+		assert!(liquids("Float").is_ok());
+		assert!(liquids("React(this: \"pitch\")").is_ok());
+		assert!(liquids("React(this: \"brine\")").is_err());
+		assert!(liquids("React(other: \"asphalt\")").is_err());
+		assert!(parse(
+			"(id: 1, name: \"tar\", state: Liquid, fluidity: 2, interactions: {\"pitch\": Sink}), \
+			(id: 2, name: \"pitch\", state: Solid)"
+		)
+		.is_err());
+	}
 }
@@ -1,22 +1,284 @@
-use crate::tiletypes::TileType;
+use crate::{
+	biomes::{depth_shift, Biome, DEPTH_SCALE},
+	tiletypes::TileType,
+	CHUNK_SIZE,
+};
+use bevy::prelude::{IVec2, Resource};
 use noise::{NoiseFn, Simplex};
 
-pub fn tiletype_at(x: i32, y: i32) -> TileType {
-	let gen_x = x as f64 * 0.025;
-	let gen_y = y as f64 * 0.025;
+pub const DEFAULT_SEED: u32 = 1337;
 
-	let simplex = Simplex::new(1337);
-	let noise = simplex.get([gen_x, gen_y]);
+const POOL_ATTEMPTS: u32 = 2;
+const POOL_SALT: u32 = 1;
+const CEILING_LIGHT_ATTEMPTS: u32 = 2;
+const CEILING_LIGHT_SALT: u32 = 2;
+/// Tiles above the surface are open sky. It rolls up to `SURFACE_AMPLITUDE` tiles above and
+/// below `SURFACE_HEIGHT`.
+const SURFACE_HEIGHT: i32 = 0;
+const SURFACE_AMPLITUDE: f64 = 16.0;
 
-	if noise < 0.0 {
-		return TileType::Empty;
+#[derive(Resource, Clone)]
+pub struct WorldGenerator {
+	seed: u32,
+	layers: Vec<(NoiseLayer, Simplex)>,
+	biome_layer: (NoiseLayer, Simplex),
+	/// The thresholds of every biome, by `Biome as usize`.
+	thresholds: Vec<Vec<Threshold>>,
+	open_sky: bool,
+}
+
+/// One octave of terrain noise. Layers are summed, so later layers with a higher
+/// `frequency` and lower `amplitude` add detail on top of the first.
+#[derive(Clone, Copy)]
+pub struct NoiseLayer {
+	pub frequency: f64,
+	pub amplitude: f64,
+}
+
+/// Summed noise below `below` produces `tile_type`. Thresholds are checked in order, and
+/// anything above the last one produces that last tile type. Each `Biome` has its own set.
+#[derive(Clone, Copy)]
+pub struct Threshold {
+	pub below: f64,
+	pub tile_type: TileType,
+	/// Whether `below` is lowered by `depth_shift` the deeper the tile is.
+	pub deepens: bool,
+}
+
+impl WorldGenerator {
+	pub fn new(seed: u32, layers: Vec<NoiseLayer>, biome_layer: NoiseLayer) -> Self {
+		Self {
+			seed,
+			biome_layer: (biome_layer, Simplex::new(seed.wrapping_sub(1))),
+			layers: layers
+				.into_iter()
+				.enumerate()
+				.map(|(i, layer)| (layer, Simplex::new(seed.wrapping_add(i as u32))))
+				.collect(),
+			thresholds: Biome::ALL.iter().map(|b| b.thresholds()).collect(),
+			open_sky: true,
+		}
+	}
+
+	/// The generator with or without a surface, see `WorldHeader::open_sky`.
+	pub fn with_open_sky(self, open_sky: bool) -> Self {
+		Self { open_sky, ..self }
+	}
+
+	pub fn from_seed(seed: u32) -> Self {
+		Self::new(
+			seed,
+			vec![NoiseLayer {
+				frequency: 0.025,
+				amplitude: 1.0,
+			}],
+			NoiseLayer {
+				frequency: 0.004,
+				amplitude: 1.0,
+			},
+		)
+	}
+
+	pub fn seed(&self) -> u32 {
+		self.seed
+	}
+
+	pub fn noise_at(&self, x: i32, y: i32) -> f64 {
+		self.layers
+			.iter()
+			.map(|(layer, simplex)| sample(layer, simplex, x, y))
+			.sum()
+	}
+
+	pub fn biome_at(&self, x: i32, y: i32) -> Biome {
+		let (layer, simplex) = &self.biome_layer;
+		Biome::from_noise(sample(layer, simplex, x, y), depth_at(y))
+	}
+
+	/// Highest tile of the terrain in column `x`, everything above it is generated empty.
+	/// Without open sky the terrain goes on forever.
+	pub fn surface_at(&self, x: i32) -> i32 {
+		if !self.open_sky {
+			return i32::MAX;
+		}
+
+		let (layer, simplex) = &self.biome_layer;
+		SURFACE_HEIGHT + (sample(layer, simplex, x, 0) * SURFACE_AMPLITUDE) as i32
 	}
 
-	if noise < 0.01 {
-		TileType::Moss
-	} else if noise > 0.2 {
-		TileType::Gravel
-	} else {
-		TileType::Dirt
+	pub fn tiletype_at(&self, x: i32, y: i32) -> TileType {
+		if y > self.surface_at(x) {
+			return TileType::EMPTY;
+		}
+
+		let noise = self.noise_at(x, y);
+		let thresholds = &self.thresholds[self.biome_at(x, y) as usize];
+		let shift = depth_shift(depth_at(y));
+
+		for threshold in thresholds.iter() {
+			let below = if threshold.deepens {
+				threshold.below - shift
+			} else {
+				threshold.below
+			};
+
+			if noise < below {
+				return threshold.tile_type;
+			}
+		}
+
+		match thresholds.last() {
+			Some(t) => t.tile_type,
+			None => TileType::EMPTY,
+		}
 	}
+
+	/// Generates the tiles of a chunk in the x-major order `spawn_chunk` iterates them:
+	/// the biome terrain first, then pools and ceiling lights on top of it.
+	pub fn generate_chunk(&self, chunk_pos: IVec2) -> Vec<TileType> {
+		let origin = chunk_origin(chunk_pos);
+		let mut tiles = vec![];
+
+		for x in 0..CHUNK_SIZE.0 as i32 {
+			for y in 0..CHUNK_SIZE.1 as i32 {
+				tiles.push(self.tiletype_at(origin.x + x, origin.y + y));
+			}
+		}
+
+		for attempt in 0..POOL_ATTEMPTS {
+			self.carve_pool(chunk_pos, &mut tiles, POOL_SALT + attempt * 16);
+		}
+
+		for attempt in 0..CEILING_LIGHT_ATTEMPTS {
+			self.place_ceiling_light(chunk_pos, &mut tiles, CEILING_LIGHT_SALT + attempt * 16);
+		}
+
+		tiles
+	}
+
+	/// Deterministic per-seed hash of a coordinate, used for every random choice made
+	/// during generation so that the same seed always produces the same world.
+	pub fn hash(&self, x: i32, y: i32, salt: u32) -> u32 {
+		let mut h = self.seed ^ salt.wrapping_mul(0x9E37_79B9);
+		h ^= (x as u32).wrapping_mul(0x85EB_CA6B);
+		h = h.rotate_left(13);
+		h ^= (y as u32).wrapping_mul(0xC2B2_AE35);
+
+		// MurmurHash3 finalizer
+		h ^= h >> 16;
+		h = h.wrapping_mul(0x85EB_CA6B);
+		h ^= h >> 13;
+		h = h.wrapping_mul(0xC2B2_AE35);
+		h ^= h >> 16;
+		h
+	}
+
+	/// Digs a bowl into a cave floor and fills it with the biome's liquid at full level.
+	/// The bowl is only kept if every tile around it is static solid ground, so the
+	/// liquid starts out at rest instead of spilling.
+	fn carve_pool(&self, chunk_pos: IVec2, tiles: &mut [TileType], salt: u32) {
+		let h = self.hash(chunk_pos.x, chunk_pos.y, salt);
+		let width = CHUNK_SIZE.0 as i32;
+		let height = CHUNK_SIZE.1 as i32;
+		let x = 6 + (h % (width as u32 - 12)) as i32;
+		let start_y = 1 + ((h >> 8) % (height as u32 - 1)) as i32;
+		let half_width = 2 + ((h >> 16) % 4) as i32;
+		let depth = 1 + ((h >> 20) % 3) as i32;
+
+		let floor_y = match (2..=start_y).rev().find(|y| {
+			!tiles[chunk_index(x, *y)].is_visible() && is_static_solid(tiles[chunk_index(x, y - 1)])
+		}) {
+			Some(v) => v,
+			None => return,
+		};
+
+		let mut cells = vec![];
+
+		for dx in -half_width..=half_width {
+			let column_depth = (depth - (dx.abs() * depth) / (half_width + 1)).max(1);
+
+			for dy in 1..=column_depth {
+				let (cx, cy) = (x + dx, floor_y - dy);
+
+				if cx < 1 || cx >= width - 1 || cy < 1 {
+					return;
+				}
+
+				cells.push((cx, cy));
+			}
+		}
+
+		for (cx, cy) in cells.iter() {
+			if !is_static_solid(tiles[chunk_index(*cx, *cy)]) {
+				return;
+			}
+
+			for (nx, ny) in [(cx - 1, *cy), (cx + 1, *cy), (*cx, cy - 1)] {
+				if !cells.contains(&(nx, ny)) && !is_static_solid(tiles[chunk_index(nx, ny)]) {
+					return;
+				}
+			}
+		}
+
+		let origin = chunk_origin(chunk_pos);
+		let liquid = self
+			.biome_at(origin.x + x, origin.y + floor_y)
+			.pool_liquid();
+
+		for (cx, cy) in cells {
+			tiles[chunk_index(cx, cy)] = liquid;
+		}
+	}
+
+	/// Hangs a light source from a cave ceiling.
+	fn place_ceiling_light(&self, chunk_pos: IVec2, tiles: &mut [TileType], salt: u32) {
+		let h = self.hash(chunk_pos.x, chunk_pos.y, salt);
+		let x = (h % CHUNK_SIZE.0 as u32) as i32;
+		let start_y = ((h >> 8) % (CHUNK_SIZE.1 as u32 - 1)) as i32;
+		let origin = chunk_origin(chunk_pos);
+
+		let (light, rarity) = match self
+			.biome_at(origin.x + x, origin.y + start_y)
+			.ceiling_light()
+		{
+			Some(v) => v,
+			None => return,
+		};
+
+		if !(h >> 16).is_multiple_of(rarity) {
+			return;
+		}
+
+		if let Some(y) = (start_y..CHUNK_SIZE.1 as i32 - 1).find(|y| {
+			!tiles[chunk_index(x, *y)].is_visible() && is_static_solid(tiles[chunk_index(x, y + 1)])
+		}) {
+			tiles[chunk_index(x, y)] = light;
+		}
+	}
+}
+
+fn chunk_origin(chunk_pos: IVec2) -> IVec2 {
+	IVec2::new(
+		chunk_pos.x * CHUNK_SIZE.0 as i32,
+		chunk_pos.y * CHUNK_SIZE.1 as i32,
+	)
+}
+
+/// Index of a chunklocal tile in the x-major tile vectors used by generation and persistence,
+/// and in the tile array of a `MapChunk`.
+pub fn chunk_index(x: i32, y: i32) -> usize {
+	(x * CHUNK_SIZE.1 as i32 + y) as usize
+}
+
+fn is_static_solid(tile_type: TileType) -> bool {
+	tile_type.is_solid() && !tile_type.is_weighted()
+}
+
+fn sample(layer: &NoiseLayer, simplex: &Simplex, x: i32, y: i32) -> f64 {
+	simplex.get([x as f64 * layer.frequency, y as f64 * layer.frequency]) * layer.amplitude
+}
+
+/// 0.0 at y = 0, increasing downwards.
+fn depth_at(y: i32) -> f64 {
+	-y as f64 / DEPTH_SCALE
 }